@@ -27,7 +27,13 @@ impl Command {
 
 fn render_game(game: &Game) {
     print!("\n{} x {} ({})\n", game.info().width, game.info().height, game.info().seed);
-    let board = &game.snapshot().board;
+    let snapshot = game.snapshot();
+    println!(
+        "time: {:.1}s  mines left: {}",
+        snapshot.elapsed_ms as f64 / 1000.0,
+        snapshot.mines_remaining
+    );
+    let board = &snapshot.board;
     board.iter().for_each(
         |row| {
             row.iter().for_each(
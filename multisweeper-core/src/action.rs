@@ -1,8 +1,19 @@
+use std::time::Duration;
+
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub enum GameAction {
     Reveal { x: u8, y: u8 },
     Flag { x: u8, y: u8 },
 }
+
+/// An applied action along with when it happened relative to the start of the game.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameMove {
+    pub action: GameAction,
+    pub at: Duration,
+    /// Time since the previous move, or since the start for the first move.
+    pub took: Duration,
+}
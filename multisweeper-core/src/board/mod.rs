@@ -1,5 +1,5 @@
 use schemars::JsonSchema;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 
 use rand::{RngExt, SeedableRng};
@@ -129,6 +129,24 @@ impl Board {
         self.created_mines.len().try_into().unwrap()
     }
 
+    pub fn flags_count(&self) -> u16 {
+        self.count_cells(|cell| cell.is_flagged && !cell.is_revealed)
+    }
+
+    pub fn revealed_count(&self) -> u16 {
+        self.count_cells(|cell| cell.is_revealed && !cell.is_mine)
+    }
+
+    fn count_cells(&self, predicate: impl Fn(&Cell) -> bool) -> u16 {
+        let count = self
+            .cells
+            .iter()
+            .flatten()
+            .filter(|cell| predicate(cell))
+            .count();
+        count.try_into().unwrap()
+    }
+
     #[tracing::instrument(skip(self))]
     pub fn reveal(&mut self, x: u8, y: u8) -> Result<RevealResult, BoardError> {
         let get_cell_result = self.get_cell_mut(x, y);
@@ -257,7 +275,7 @@ impl Cell {
     }
 }
 
-#[derive(Serialize, Debug, Clone, JsonSchema)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub enum CellView {
     HiddenCell,
     VisibleCell(u8),
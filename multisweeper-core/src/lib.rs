@@ -1,11 +1,15 @@
 mod action;
 mod board;
+mod clock;
 mod error;
 mod state;
 
+use std::time::{Duration, Instant};
+
 use board::{Board, RevealResult};
 
-pub use action::GameAction;
+pub use action::{GameAction, GameMove};
+pub use clock::{Clock, ManualClock, SystemClock};
 pub use error::*;
 pub use state::*;
 
@@ -31,25 +35,43 @@ pub struct Game {
     pub difficulty: GameDifficulty,
     board: Board,
     last_state: GameSnapshot,
+    clock: Box<dyn Clock>,
+    started_at: Instant,
+    ended_at: Option<Instant>,
+    moves: Vec<GameMove>,
 }
 
 impl Game {
-    #[tracing::instrument]
     pub fn new(difficulty: GameDifficulty, seed: u64) -> GameResult<Self> {
+        Self::with_clock(difficulty, seed, SystemClock)
+    }
+
+    #[tracing::instrument(skip(clock))]
+    pub fn with_clock(
+        difficulty: GameDifficulty,
+        seed: u64,
+        clock: impl Clock + 'static,
+    ) -> GameResult<Self> {
         let board = Board::new(
             (difficulty as u8) * 4,
             (difficulty as u8) * 4,
             (difficulty as u8) * 3,
             seed,
         )?;
+        let started_at = clock.now();
         Ok(Game {
             board: board.clone(),
-            last_state: GameSnapshot {
-                status: GameStatus::Playing,
-                action_result: GameActionResult::Started,
-                board: board.expose_cells(),
-            },
+            last_state: capture_snapshot(
+                &board,
+                GameStatus::Playing,
+                GameActionResult::Started,
+                Duration::ZERO,
+            ),
             difficulty,
+            clock: Box::new(clock),
+            started_at,
+            ended_at: None,
+            moves: Vec::new(),
         })
     }
 
@@ -62,22 +84,73 @@ impl Game {
         };
     }
 
-    fn expose_board(&self) -> Vec<Vec<GameCell>> {
-        return self.board.expose_cells();
+    /// Rebuilds a game by replaying `moves`, resuming the clock from the time of the last move.
+    pub fn replay(
+        difficulty: GameDifficulty,
+        seed: u64,
+        moves: &[GameMove],
+        clock: impl Clock + 'static,
+    ) -> GameResult<Self> {
+        let mut game = Self::with_clock(difficulty, seed, clock)?;
+        for recorded in moves {
+            game.handle_action(recorded.action)?;
+        }
+        let resumed_at = moves.last().map_or(Duration::ZERO, |last| last.at);
+        let now = game.clock.now();
+        game.started_at = now.checked_sub(resumed_at).unwrap_or(now);
+        if game.ended_at.is_some() {
+            game.ended_at = Some(now);
+        }
+        game.moves = moves.to_vec();
+        Ok(game)
+    }
+
+    pub fn started_at(&self) -> Instant {
+        self.started_at
+    }
+
+    /// When the game was won or lost, if it has concluded.
+    pub fn ended_at(&self) -> Option<Instant> {
+        self.ended_at
+    }
+
+    /// Time since the game started, frozen once the game concludes.
+    pub fn elapsed(&self) -> Duration {
+        let until = self.ended_at.unwrap_or_else(|| self.clock.now());
+        until.saturating_duration_since(self.started_at)
+    }
+
+    pub fn moves(&self) -> &[GameMove] {
+        &self.moves
     }
 
     fn set_state(&mut self, outcome: ActionOutcome) -> &GameSnapshot {
-        let new_state = GameSnapshot {
-            status: outcome.into(),
-            action_result: outcome.into(),
-            board: self.expose_board(),
-        };
+        let status: GameStatus = outcome.into();
+        if status != GameStatus::Playing {
+            self.ended_at = Some(self.clock.now());
+        }
+        let new_state = capture_snapshot(&self.board, status, outcome.into(), self.elapsed());
         self.last_state = new_state.clone();
         return &self.last_state;
     }
 
-    pub fn snapshot(&self) -> &GameSnapshot {
-        return &self.last_state;
+    /// The state after the last action, with the elapsed time as of now.
+    pub fn snapshot(&self) -> GameSnapshot {
+        GameSnapshot {
+            elapsed_ms: duration_ms(self.elapsed()),
+            ..self.last_state.clone()
+        }
+    }
+
+    fn record_move(&mut self, action: GameAction) {
+        let now = self.clock.now();
+        let previous = self.moves.last().map_or(Duration::ZERO, |last| last.at);
+        let at = now.saturating_duration_since(self.started_at);
+        self.moves.push(GameMove {
+            action,
+            at,
+            took: at.saturating_sub(previous),
+        });
     }
 
     #[tracing::instrument(skip(self))]
@@ -109,6 +182,7 @@ impl Game {
         match action {
             GameAction::Reveal { x, y } => {
                 let reveal = self.reveal(x, y)?;
+                self.record_move(action);
                 if ActionOutcome::Playing == reveal && self.board.is_all_safe_cells_revealed() {
                     return Ok(self.set_state(ActionOutcome::Won));
                 }
@@ -116,6 +190,7 @@ impl Game {
             }
             GameAction::Flag { x, y } => {
                 let flag = self.flag(x, y)?;
+                self.record_move(action);
                 return Ok(self.set_state(flag));
             }
         }
@@ -123,10 +198,136 @@ impl Game {
 
     pub fn lose_game(&mut self) {
         self.board.reveal_all();
-        self.last_state = GameSnapshot {
-            status: GameStatus::NoWinner,
-            action_result: GameActionResult::Eliminated,
-            board: self.expose_board(),
-        }
+        self.ended_at.get_or_insert_with(|| self.clock.now());
+        self.last_state = capture_snapshot(
+            &self.board,
+            GameStatus::NoWinner,
+            GameActionResult::Eliminated,
+            self.elapsed(),
+        );
+    }
+}
+
+fn capture_snapshot(
+    board: &Board,
+    status: GameStatus,
+    action_result: GameActionResult,
+    elapsed: Duration,
+) -> GameSnapshot {
+    let mines = board.mines_count();
+    let flags = board.flags_count();
+    GameSnapshot {
+        status,
+        action_result,
+        elapsed_ms: duration_ms(elapsed),
+        width: board.width,
+        height: board.height,
+        mines,
+        flags,
+        mines_remaining: i16::from(mines) - flags as i16,
+        revealed: board.revealed_count(),
+        board: board.expose_cells(),
+    }
+}
+
+fn duration_ms(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Game, GameAction, GameDifficulty, GameStatus, ManualClock};
+
+    #[test]
+    fn elapsed_time_follows_the_clock_while_playing() {
+        let clock = ManualClock::new();
+        let game = Game::with_clock(GameDifficulty::TEST, 7, clock.clone()).expect("valid game");
+
+        clock.advance(Duration::from_millis(1500));
+
+        assert_eq!(game.elapsed(), Duration::from_millis(1500));
+        assert_eq!(game.snapshot().elapsed_ms, 1500);
+        assert!(game.ended_at().is_none());
+    }
+
+    #[test]
+    fn moves_record_time_since_the_previous_move() {
+        let clock = ManualClock::new();
+        let mut game =
+            Game::with_clock(GameDifficulty::TEST, 7, clock.clone()).expect("valid game");
+
+        clock.advance(Duration::from_secs(2));
+        game.handle_action(GameAction::Flag { x: 0, y: 0 })
+            .expect("flag should apply");
+        clock.advance(Duration::from_secs(3));
+        game.handle_action(GameAction::Flag { x: 0, y: 0 })
+            .expect("unflag should apply");
+
+        let moves = game.moves();
+        assert_eq!(moves.len(), 2);
+        assert_eq!(moves[0].at, Duration::from_secs(2));
+        assert_eq!(moves[0].took, Duration::from_secs(2));
+        assert_eq!(moves[1].at, Duration::from_secs(5));
+        assert_eq!(moves[1].took, Duration::from_secs(3));
+    }
+
+    #[test]
+    fn snapshots_carry_board_metadata_and_flag_counts() {
+        let mut game = Game::new(GameDifficulty::TEST, 7).expect("valid game");
+        let mines = game.info().number_of_mines;
+
+        let snapshot = game
+            .handle_action(GameAction::Flag { x: 1, y: 2 })
+            .expect("flag should apply");
+
+        assert_eq!((snapshot.width, snapshot.height), (4, 4));
+        assert_eq!(snapshot.mines, mines);
+        assert_eq!(snapshot.flags, 1);
+        assert_eq!(snapshot.mines_remaining, i16::from(mines) - 1);
+        assert_eq!(snapshot.revealed, 0);
+    }
+
+    #[test]
+    fn replayed_games_match_the_original_board_and_clock() {
+        let clock = ManualClock::new();
+        let mut game =
+            Game::with_clock(GameDifficulty::TEST, 11, clock.clone()).expect("valid game");
+        clock.advance(Duration::from_secs(3));
+        game.handle_action(GameAction::Flag { x: 2, y: 2 })
+            .expect("flag should apply");
+        clock.advance(Duration::from_secs(2));
+        let _ = game.handle_action(GameAction::Reveal { x: 0, y: 0 });
+
+        let replayed = Game::replay(GameDifficulty::TEST, 11, game.moves(), ManualClock::new())
+            .expect("moves should replay");
+
+        let (original, restored) = (game.snapshot(), replayed.snapshot());
+        assert_eq!(restored.board.len(), original.board.len());
+        assert_eq!(restored.flags, original.flags);
+        assert_eq!(restored.revealed, original.revealed);
+        assert_eq!(restored.status, original.status);
+        assert_eq!(replayed.moves().len(), game.moves().len());
+        assert_eq!(replayed.elapsed(), game.moves().last().unwrap().at);
+    }
+
+    #[test]
+    fn elapsed_time_freezes_when_the_game_concludes() {
+        let clock = ManualClock::new();
+        let mut game =
+            Game::with_clock(GameDifficulty::TEST, 7, clock.clone()).expect("valid game");
+
+        clock.advance(Duration::from_secs(4));
+        game.lose_game();
+        clock.advance(Duration::from_secs(10));
+
+        let snapshot = game.snapshot();
+        assert_eq!(snapshot.status, GameStatus::NoWinner);
+        assert_eq!(snapshot.elapsed_ms, 4000);
+        assert_eq!(
+            game.ended_at(),
+            Some(game.started_at() + Duration::from_secs(4))
+        );
     }
 }
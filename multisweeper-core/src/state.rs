@@ -1,16 +1,28 @@
 use schemars::JsonSchema;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 pub use crate::board::CellView as GameCell;
 
-#[derive(Serialize, Debug, Clone, JsonSchema)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct GameSnapshot {
     pub status: GameStatus,
     pub action_result: GameActionResult,
+    /// Milliseconds since the game started, frozen once it concludes.
+    pub elapsed_ms: u64,
+    pub width: u8,
+    pub height: u8,
+    pub mines: u8,
+    /// Cells currently flagged, whether or not they hold a mine.
+    pub flags: u16,
+    /// Mines minus flags; negative when more cells are flagged than there are mines.
+    pub mines_remaining: i16,
+    /// Safe cells revealed so far.
+    pub revealed: u16,
+    /// Rows of cells, top to bottom.
     pub board: Vec<Vec<GameCell>>,
 }
 
-#[derive(Serialize, Debug, Clone, JsonSchema)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
 pub enum GameStatus {
     Won,
     NoWinner,
@@ -29,7 +41,7 @@ impl From<ActionOutcome> for GameStatus {
     }
 }
 
-#[derive(Serialize, Debug, Clone, JsonSchema)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub enum GameActionResult {
     Applied,
     Stalled,
@@ -0,0 +1,263 @@
+use std::{collections::HashMap, fs, io, path::PathBuf};
+
+use rand::random;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::persistence::write_atomically;
+
+pub type AccountId = String;
+pub type AccountToken = String;
+
+const MAX_DISPLAY_NAME_CHARS: usize = 24;
+
+#[derive(Debug, Error)]
+pub enum AccountError {
+    #[error("no account matches the given token")]
+    NotFound,
+    #[error("invalid display name: {0}")]
+    InvalidDisplayName(String),
+    #[error("account store io failed: {0}")]
+    Io(#[from] io::Error),
+    #[error("account store is corrupt: {0}")]
+    Corrupt(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Account {
+    pub id: AccountId,
+    pub display_name: String,
+}
+
+/// An account together with the secret token a client presents to reclaim it.
+#[derive(Debug, Clone)]
+pub struct Identity {
+    pub token: AccountToken,
+    pub account: Account,
+}
+
+/// Accounts keyed by their token, optionally mirrored to a JSON file so they survive restarts.
+pub struct AccountStore {
+    path: Option<PathBuf>,
+    accounts: HashMap<AccountToken, Account>,
+    /// Set when the accounts differ from what was last written to the file.
+    unsaved: bool,
+}
+
+/// The store's file contents, taken so they can be written away from the caller's task.
+pub struct PendingSave {
+    path: PathBuf,
+    contents: Vec<u8>,
+}
+
+impl PendingSave {
+    pub fn write(&self) -> Result<(), AccountError> {
+        write_atomically(&self.path, &self.contents)?;
+        Ok(())
+    }
+}
+
+impl AccountStore {
+    pub fn in_memory() -> Self {
+        AccountStore {
+            path: None,
+            accounts: HashMap::new(),
+            unsaved: false,
+        }
+    }
+
+    /// Loads the store at `path`, starting empty if the file does not exist yet.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, AccountError> {
+        let path = path.into();
+        let accounts = match fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents)?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(error) => return Err(error.into()),
+        };
+        Ok(AccountStore {
+            path: Some(path),
+            accounts,
+            unsaved: false,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    /// Creates a new account, or reclaims the one behind `token` and optionally renames it.
+    pub fn identify(
+        &mut self,
+        token: Option<AccountToken>,
+        display_name: Option<String>,
+    ) -> Result<Identity, AccountError> {
+        let display_name = display_name
+            .map(|name| validate_display_name(&name))
+            .transpose()?;
+        let identity = match token {
+            Some(token) => {
+                let account = self
+                    .accounts
+                    .get_mut(&token)
+                    .ok_or(AccountError::NotFound)?;
+                if let Some(display_name) = display_name
+                    && display_name != account.display_name
+                {
+                    account.display_name = display_name;
+                    self.unsaved = true;
+                }
+                Identity {
+                    token,
+                    account: account.clone(),
+                }
+            }
+            None => {
+                let display_name = display_name.ok_or_else(|| {
+                    AccountError::InvalidDisplayName(
+                        "a display name is required to create an account".to_string(),
+                    )
+                })?;
+                let identity = Identity {
+                    token: format!("{:032x}", random::<u128>()),
+                    account: Account {
+                        id: format!("A{:016x}", random::<u64>()),
+                        display_name,
+                    },
+                };
+                self.accounts
+                    .insert(identity.token.clone(), identity.account.clone());
+                self.unsaved = true;
+                identity
+            }
+        };
+        Ok(identity)
+    }
+
+    /// Drops the account behind `token`, such as one whose creation could not be saved.
+    pub fn forget(&mut self, token: &AccountToken) {
+        self.accounts.remove(token);
+    }
+
+    /// What to write to the store's file, if it has one and anything changed since the last
+    /// save. Call [`AccountStore::mark_saved`] once it is written.
+    pub fn pending_save(&self) -> Result<Option<PendingSave>, AccountError> {
+        match &self.path {
+            Some(path) if self.unsaved => Ok(Some(PendingSave {
+                path: path.clone(),
+                contents: serde_json::to_vec_pretty(&self.accounts)?,
+            })),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.unsaved = false;
+    }
+
+    /// Writes the store to its file, if it has one and anything changed.
+    pub fn save(&mut self) -> Result<(), AccountError> {
+        if let Some(pending) = self.pending_save()? {
+            pending.write()?;
+            self.mark_saved();
+        }
+        Ok(())
+    }
+}
+
+fn validate_display_name(name: &str) -> Result<String, AccountError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AccountError::InvalidDisplayName(
+            "must not be empty".to_string(),
+        ));
+    }
+    if name.chars().count() > MAX_DISPLAY_NAME_CHARS {
+        return Err(AccountError::InvalidDisplayName(format!(
+            "must be at most {MAX_DISPLAY_NAME_CHARS} characters"
+        )));
+    }
+    if name.chars().any(char::is_control) {
+        return Err(AccountError::InvalidDisplayName(
+            "must not contain control characters".to_string(),
+        ));
+    }
+    Ok(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AccountError, AccountStore};
+
+    #[test]
+    fn tokens_reclaim_and_rename_accounts() {
+        let mut store = AccountStore::in_memory();
+        let created = store
+            .identify(None, Some("  Ada ".to_string()))
+            .expect("account should be created");
+        assert_eq!(created.account.display_name, "Ada");
+
+        let reclaimed = store
+            .identify(Some(created.token.clone()), None)
+            .expect("token should reclaim the account");
+        assert_eq!(reclaimed.account, created.account);
+
+        let renamed = store
+            .identify(Some(created.token), Some("Grace".to_string()))
+            .expect("token should allow renaming");
+        assert_eq!(renamed.account.id, created.account.id);
+        assert_eq!(renamed.account.display_name, "Grace");
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn unknown_tokens_and_bad_names_are_rejected() {
+        let mut store = AccountStore::in_memory();
+
+        assert!(matches!(
+            store.identify(Some("nope".to_string()), None),
+            Err(AccountError::NotFound)
+        ));
+        assert!(matches!(
+            store.identify(None, None),
+            Err(AccountError::InvalidDisplayName(_))
+        ));
+        assert!(matches!(
+            store.identify(None, Some("a\u{7}b".to_string())),
+            Err(AccountError::InvalidDisplayName(_))
+        ));
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn file_backed_stores_survive_reopening() {
+        let path = std::env::temp_dir().join(format!(
+            "multisweeper-accounts-{:016x}.json",
+            rand::random::<u64>()
+        ));
+        let mut store = AccountStore::open(&path).expect("missing file opens empty");
+        let identity = store
+            .identify(None, Some("Ada".to_string()))
+            .expect("account should be created");
+        store.save().expect("store should save");
+        assert!(store.pending_save().expect("store serializes").is_none());
+        store
+            .identify(Some(identity.token.clone()), Some("Ada".to_string()))
+            .expect("token should reclaim the account");
+        assert!(
+            store.pending_save().expect("store serializes").is_none(),
+            "reclaiming without a change needs no save"
+        );
+
+        let mut reopened = AccountStore::open(&path).expect("saved store should load");
+        let reclaimed = reopened
+            .identify(Some(identity.token), None)
+            .expect("token should survive reopening");
+        assert_eq!(reclaimed.account, identity.account);
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use thiserror::Error;
+
+use crate::{protocol::session::ChatMessageView, session::PlayerId};
+
+pub const MAX_CHAT_CHARS: usize = 280;
+const HISTORY_LEN: usize = 50;
+const RATE_WINDOW: Duration = Duration::from_secs(10);
+const RATE_LIMIT: usize = 5;
+
+#[derive(Debug, Clone, Error)]
+pub enum ChatError {
+    #[error("chat message must not be empty")]
+    Empty,
+    #[error("chat message must be at most {MAX_CHAT_CHARS} characters")]
+    TooLong,
+    #[error("sending chat messages too quickly")]
+    RateLimited,
+}
+
+/// A room's recent chat plus the send times used to rate limit each player. Send times outlive
+/// a player's seat, so leaving and rejoining does not reset the limit.
+#[derive(Default)]
+pub struct ChatLog {
+    history: VecDeque<ChatMessageView>,
+    recent_sends: HashMap<PlayerId, VecDeque<Instant>>,
+}
+
+impl ChatLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Validates and records a message, returning the entry to broadcast.
+    pub fn post(
+        &mut self,
+        sender: &PlayerId,
+        display_name: Option<String>,
+        text: &str,
+        now: Instant,
+        sent_at: u64,
+    ) -> Result<ChatMessageView, ChatError> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(ChatError::Empty);
+        }
+        if text.chars().count() > MAX_CHAT_CHARS {
+            return Err(ChatError::TooLong);
+        }
+
+        for sends in self.recent_sends.values_mut() {
+            while sends
+                .front()
+                .is_some_and(|sent| now.saturating_duration_since(*sent) >= RATE_WINDOW)
+            {
+                sends.pop_front();
+            }
+        }
+        self.recent_sends.retain(|_, sends| !sends.is_empty());
+        let sends = self.recent_sends.entry(sender.clone()).or_default();
+        if sends.len() >= RATE_LIMIT {
+            return Err(ChatError::RateLimited);
+        }
+        sends.push_back(now);
+
+        let entry = ChatMessageView {
+            sender: sender.clone(),
+            display_name,
+            text: text.to_string(),
+            sent_at,
+        };
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(entry.clone());
+        Ok(entry)
+    }
+
+    pub fn history(&self) -> Vec<ChatMessageView> {
+        self.history.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{ChatError, ChatLog, HISTORY_LEN, MAX_CHAT_CHARS, RATE_LIMIT, RATE_WINDOW};
+
+    #[test]
+    fn messages_are_trimmed_and_length_checked() {
+        let mut chat = ChatLog::new();
+        let sender = "P00001".to_string();
+        let now = Instant::now();
+
+        let entry = chat
+            .post(&sender, None, "  gg  ", now, 0)
+            .expect("message should post");
+        assert_eq!(entry.text, "gg");
+        assert!(matches!(
+            chat.post(&sender, None, "   ", now, 0),
+            Err(ChatError::Empty)
+        ));
+        assert!(matches!(
+            chat.post(&sender, None, &"x".repeat(MAX_CHAT_CHARS + 1), now, 0),
+            Err(ChatError::TooLong)
+        ));
+    }
+
+    #[test]
+    fn senders_are_rate_limited_within_the_window() {
+        let mut chat = ChatLog::new();
+        let (ada, bob) = ("P00001".to_string(), "P00002".to_string());
+        let now = Instant::now();
+
+        for _ in 0..RATE_LIMIT {
+            chat.post(&ada, None, "hi", now, 0).expect("within limit");
+        }
+        assert!(matches!(
+            chat.post(&ada, None, "hi", now, 0),
+            Err(ChatError::RateLimited)
+        ));
+        chat.post(&bob, None, "hi", now, 0)
+            .expect("limits are per player");
+        chat.post(&ada, None, "hi", now + RATE_WINDOW, 0)
+            .expect("window should have passed");
+    }
+
+    #[test]
+    fn history_keeps_only_the_latest_messages() {
+        let mut chat = ChatLog::new();
+        let start = Instant::now();
+        for index in 0..HISTORY_LEN + 3 {
+            let sender = format!("P{index:05}");
+            let now = start + Duration::from_secs(index as u64);
+            chat.post(&sender, None, &index.to_string(), now, 0)
+                .expect("message should post");
+        }
+
+        let history = chat.history();
+        assert_eq!(history.len(), HISTORY_LEN);
+        assert_eq!(history[0].text, "3");
+    }
+}
@@ -0,0 +1,412 @@
+use std::{
+    fs, io,
+    net::{IpAddr, Ipv4Addr},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use clap::{Parser, ValueEnum};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{registry::RoomLimits, session::Heartbeat};
+
+const DEFAULT_PORT: u16 = 8080;
+const DEFAULT_HTTP_PORT: u16 = 8081;
+const DEFAULT_ADMIN_PORT: u16 = 8082;
+const DEFAULT_MAILBOX_CAPACITY: usize = 10;
+const DEFAULT_SHUTDOWN_TIMEOUT_SECS: u64 = 10;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("could not read config file {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+    #[error("config file {path} is invalid: {source}")]
+    Parse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error("invalid configuration: {0}")]
+    Invalid(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Compact,
+    Json,
+}
+
+/// Command-line flags. Each one can also come from its `SERVER_*` environment variable, and
+/// anything left unset falls back to the config file and then the built-in default.
+#[derive(Parser, Debug, Default)]
+#[command(version, about, long_about=None)]
+pub struct Args {
+    #[clap(
+        long,
+        env("SERVER_CONFIG"),
+        help("Read settings from this JSON file; flags and environment variables override it")
+    )]
+    pub config: Option<PathBuf>,
+    #[clap(
+        long,
+        env("SERVER_BIND_ADDRESS"),
+        help("Address to listen on (default: 0.0.0.0)")
+    )]
+    pub bind_address: Option<IpAddr>,
+    #[clap(
+        long,
+        env("SERVER_PORT"),
+        help("Port to expose the server on (default: 8080)")
+    )]
+    pub port: Option<u16>,
+    #[clap(
+        long,
+        env("SERVER_HTTP_PORT"),
+        help(
+            "Port for the /healthz, /readyz, /status and /metrics HTTP endpoints (default: 8081)"
+        )
+    )]
+    pub http_port: Option<u16>,
+    #[clap(
+        long,
+        env("SERVER_MAILBOX_CAPACITY"),
+        help("Messages each session, room and the registry may have queued (default: 10)")
+    )]
+    pub mailbox_capacity: Option<usize>,
+    #[clap(
+        long,
+        env("SERVER_MAX_ROOMS"),
+        help("Rooms that may exist at once (default: 1000)")
+    )]
+    pub max_rooms: Option<usize>,
+    #[clap(
+        long,
+        env("SERVER_MAX_PLAYERS_PER_ROOM"),
+        help("Players and spectators a room may hold (default: 16)")
+    )]
+    pub max_players_per_room: Option<usize>,
+    #[clap(
+        long,
+        env("SERVER_PING_INTERVAL_SECS"),
+        help("Seconds between server pings on each connection (default: 15)")
+    )]
+    pub ping_interval_secs: Option<u64>,
+    #[clap(
+        long,
+        env("SERVER_IDLE_TIMEOUT_SECS"),
+        help("Close connections that send nothing for this many seconds (default: 45)")
+    )]
+    pub idle_timeout_secs: Option<u64>,
+    #[clap(
+        long,
+        env("SERVER_SHUTDOWN_TIMEOUT_SECS"),
+        help("Seconds to wait for rooms and sessions to wind down on shutdown (default: 10)")
+    )]
+    pub shutdown_timeout_secs: Option<u64>,
+    #[clap(
+        long,
+        env("SERVER_RESTART_ETA_SECS"),
+        help("Tell clients the server expects to be back this many seconds after shutting down")
+    )]
+    pub restart_eta_secs: Option<u32>,
+    #[clap(
+        long,
+        env("SERVER_LOG_FORMAT"),
+        help("Log output format (default: text)")
+    )]
+    pub log_format: Option<LogFormat>,
+    #[clap(
+        long,
+        env("SERVER_ACCOUNTS_FILE"),
+        help("Persist player accounts to this JSON file (default: accounts are kept in memory)")
+    )]
+    pub accounts_file: Option<PathBuf>,
+    #[clap(
+        long,
+        env("SERVER_STATS_FILE"),
+        help(
+            "Append finished matches to this JSON-lines file (default: stats are kept in memory)"
+        )
+    )]
+    pub stats_file: Option<PathBuf>,
+    #[clap(
+        long,
+        env("SERVER_ROOMS_DIR"),
+        help(
+            "Snapshot rooms into this directory and restore them on boot (default: rooms are not persisted)"
+        )
+    )]
+    pub rooms_dir: Option<PathBuf>,
+    #[clap(
+        long,
+        env("SERVER_TLS_CERT"),
+        help("Serve wss:// using this PEM certificate chain (default: plain ws://)")
+    )]
+    pub tls_cert: Option<PathBuf>,
+    #[clap(long, env("SERVER_TLS_KEY"), help("PEM private key for --tls-cert"))]
+    pub tls_key: Option<PathBuf>,
+    #[clap(
+        long,
+        env("SERVER_ADMIN_TOKEN"),
+        hide_env_values(true),
+        help("Bearer token for the /admin endpoints (default: admin endpoints disabled)")
+    )]
+    pub admin_token: Option<String>,
+    #[clap(
+        long,
+        env("SERVER_ADMIN_BIND_ADDRESS"),
+        help(
+            "Address the /admin endpoints listen on; the token is sent in cleartext (default: 127.0.0.1)"
+        )
+    )]
+    pub admin_bind_address: Option<IpAddr>,
+    #[clap(
+        long,
+        env("SERVER_ADMIN_PORT"),
+        help("Port for the /admin endpoints (default: 8082)")
+    )]
+    pub admin_port: Option<u16>,
+}
+
+/// The config file: the same settings as [`Args`], all optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileConfig {
+    pub bind_address: Option<IpAddr>,
+    pub port: Option<u16>,
+    pub http_port: Option<u16>,
+    pub mailbox_capacity: Option<usize>,
+    pub max_rooms: Option<usize>,
+    pub max_players_per_room: Option<usize>,
+    pub ping_interval_secs: Option<u64>,
+    pub idle_timeout_secs: Option<u64>,
+    pub shutdown_timeout_secs: Option<u64>,
+    pub restart_eta_secs: Option<u32>,
+    pub log_format: Option<LogFormat>,
+    pub accounts_file: Option<PathBuf>,
+    pub stats_file: Option<PathBuf>,
+    pub rooms_dir: Option<PathBuf>,
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+    pub admin_token: Option<String>,
+    pub admin_bind_address: Option<IpAddr>,
+    pub admin_port: Option<u16>,
+}
+
+impl FileConfig {
+    pub fn open(path: &Path) -> Result<Self, ConfigError> {
+        let contents = fs::read(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        serde_json::from_slice(&contents).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub bind_address: IpAddr,
+    pub port: u16,
+    pub http_port: u16,
+    pub mailbox_capacity: usize,
+    pub room_limits: RoomLimits,
+    pub heartbeat: Heartbeat,
+    pub shutdown_timeout: Duration,
+    pub restart_eta_secs: Option<u32>,
+    pub log_format: LogFormat,
+    pub accounts_file: Option<PathBuf>,
+    pub stats_file: Option<PathBuf>,
+    pub rooms_dir: Option<PathBuf>,
+    /// Certificate chain and private key paths, when serving `wss://`.
+    pub tls: Option<(PathBuf, PathBuf)>,
+    /// Token operators present to use the admin endpoints, which are off without one.
+    pub admin_token: Option<String>,
+    /// The admin endpoints get their own plain HTTP listener, on loopback unless overridden.
+    pub admin_bind_address: IpAddr,
+    pub admin_port: u16,
+}
+
+impl Config {
+    /// Reads the command line, environment and config file, in that order of precedence.
+    pub fn load() -> Result<Self, ConfigError> {
+        let args = Args::parse();
+        let file = match &args.config {
+            Some(path) => FileConfig::open(path)?,
+            None => FileConfig::default(),
+        };
+        Self::resolve(args, file)
+    }
+
+    pub fn resolve(args: Args, file: FileConfig) -> Result<Self, ConfigError> {
+        let default_heartbeat = Heartbeat::default();
+        let default_limits = RoomLimits::default();
+        let config = Config {
+            bind_address: args
+                .bind_address
+                .or(file.bind_address)
+                .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            port: args.port.or(file.port).unwrap_or(DEFAULT_PORT),
+            http_port: args
+                .http_port
+                .or(file.http_port)
+                .unwrap_or(DEFAULT_HTTP_PORT),
+            mailbox_capacity: args
+                .mailbox_capacity
+                .or(file.mailbox_capacity)
+                .unwrap_or(DEFAULT_MAILBOX_CAPACITY),
+            room_limits: RoomLimits {
+                max_rooms: args
+                    .max_rooms
+                    .or(file.max_rooms)
+                    .unwrap_or(default_limits.max_rooms),
+                max_players: args
+                    .max_players_per_room
+                    .or(file.max_players_per_room)
+                    .unwrap_or(default_limits.max_players),
+            },
+            heartbeat: Heartbeat {
+                ping_interval: args
+                    .ping_interval_secs
+                    .or(file.ping_interval_secs)
+                    .map_or(default_heartbeat.ping_interval, Duration::from_secs),
+                idle_timeout: args
+                    .idle_timeout_secs
+                    .or(file.idle_timeout_secs)
+                    .map_or(default_heartbeat.idle_timeout, Duration::from_secs),
+            },
+            shutdown_timeout: Duration::from_secs(
+                args.shutdown_timeout_secs
+                    .or(file.shutdown_timeout_secs)
+                    .unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT_SECS),
+            ),
+            restart_eta_secs: args.restart_eta_secs.or(file.restart_eta_secs),
+            log_format: args.log_format.or(file.log_format).unwrap_or_default(),
+            accounts_file: args.accounts_file.or(file.accounts_file),
+            stats_file: args.stats_file.or(file.stats_file),
+            rooms_dir: args.rooms_dir.or(file.rooms_dir),
+            tls: match (
+                args.tls_cert.or(file.tls_cert),
+                args.tls_key.or(file.tls_key),
+            ) {
+                (Some(cert), Some(key)) => Some((cert, key)),
+                (None, None) => None,
+                _ => {
+                    return Err(ConfigError::Invalid(
+                        "tls_cert and tls_key must be set together",
+                    ));
+                }
+            },
+            admin_token: args.admin_token.or(file.admin_token),
+            admin_bind_address: args
+                .admin_bind_address
+                .or(file.admin_bind_address)
+                .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            admin_port: args
+                .admin_port
+                .or(file.admin_port)
+                .unwrap_or(DEFAULT_ADMIN_PORT),
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.http_port == self.port {
+            return Err(ConfigError::Invalid("http_port must differ from port"));
+        }
+        if self.mailbox_capacity == 0 {
+            return Err(ConfigError::Invalid("mailbox_capacity must be at least 1"));
+        }
+        if self.room_limits.max_rooms == 0 {
+            return Err(ConfigError::Invalid("max_rooms must be at least 1"));
+        }
+        if self.room_limits.max_players == 0 {
+            return Err(ConfigError::Invalid(
+                "max_players_per_room must be at least 1",
+            ));
+        }
+        if self.heartbeat.ping_interval.is_zero() {
+            return Err(ConfigError::Invalid(
+                "ping_interval_secs must be at least 1",
+            ));
+        }
+        if self.heartbeat.idle_timeout <= self.heartbeat.ping_interval {
+            return Err(ConfigError::Invalid(
+                "idle_timeout_secs must be longer than ping_interval_secs",
+            ));
+        }
+        if self
+            .admin_token
+            .as_ref()
+            .is_some_and(|token| token.trim().is_empty())
+        {
+            return Err(ConfigError::Invalid("admin_token must not be empty"));
+        }
+        if self.admin_token.is_some()
+            && (self.admin_port == self.port || self.admin_port == self.http_port)
+        {
+            return Err(ConfigError::Invalid(
+                "admin_port must differ from port and http_port",
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::IpAddr, path::PathBuf, time::Duration};
+
+    use super::{Args, Config, ConfigError, FileConfig, LogFormat};
+
+    #[test]
+    fn flags_override_the_file_which_overrides_defaults() {
+        let file: FileConfig = serde_json::from_str(
+            r#"{ "port": 9000, "bind_address": "127.0.0.1", "log_format": "json", "max_rooms": 5 }"#,
+        )
+        .expect("file config should parse");
+        let args = Args {
+            port: Some(9100),
+            rooms_dir: Some(PathBuf::from("rooms")),
+            ..Args::default()
+        };
+
+        let config = Config::resolve(args, file).expect("config should be valid");
+        assert_eq!(config.port, 9100);
+        assert_eq!(config.bind_address, "127.0.0.1".parse::<IpAddr>().unwrap());
+        assert_eq!(config.log_format, LogFormat::Json);
+        assert_eq!(config.room_limits.max_rooms, 5);
+        assert_eq!(config.rooms_dir, Some(PathBuf::from("rooms")));
+        assert_eq!(config.heartbeat.ping_interval, Duration::from_secs(15));
+        assert!(config.admin_bind_address.is_loopback());
+        assert!(serde_json::from_str::<FileConfig>(r#"{ "prot": 1 }"#).is_err());
+    }
+
+    #[test]
+    fn inconsistent_settings_are_rejected() {
+        let idle_before_ping = Args {
+            ping_interval_secs: Some(30),
+            idle_timeout_secs: Some(30),
+            ..Args::default()
+        };
+        assert!(matches!(
+            Config::resolve(idle_before_ping, FileConfig::default()),
+            Err(ConfigError::Invalid(_))
+        ));
+
+        let half_tls = FileConfig {
+            tls_cert: Some(PathBuf::from("cert.pem")),
+            ..FileConfig::default()
+        };
+        assert!(matches!(
+            Config::resolve(Args::default(), half_tls),
+            Err(ConfigError::Invalid(_))
+        ));
+    }
+}
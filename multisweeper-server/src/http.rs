@@ -0,0 +1,238 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    Json, Router,
+    extract::{Path, Request, State},
+    http::{HeaderMap, StatusCode, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::oneshot::{self, Sender},
+    time::timeout,
+};
+
+use crate::{
+    metrics::METRICS,
+    protocol::{registry::RegistryMessage, room::RoomControl},
+    registry::{RegistryAddr, ServerStatus, SessionSummary},
+    room::RoomState,
+};
+
+/// How long the registry gets to answer before the server is reported as not ready.
+const REGISTRY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Shown to players removed by an admin request.
+const ADMIN_REASON: &str = "closed by a server administrator";
+
+/// Routes for the plain HTTP side port: liveness, readiness, a status summary and metrics.
+pub fn router(registry: RegistryAddr) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/status", get(status))
+        .route("/metrics", get(metrics))
+        .with_state(registry)
+}
+
+/// The `/admin` endpoints, served on their own listener so they can stay off public addresses.
+pub fn admin_router(registry: RegistryAddr, token: &str) -> Router {
+    let admin = Router::new()
+        .route("/rooms", get(list_rooms))
+        .route("/rooms/{code}", delete(close_room))
+        .route("/sessions", get(list_sessions))
+        .route("/sessions/{id}", delete(disconnect_session))
+        .route("/announcements", post(announce))
+        .route_layer(middleware::from_fn_with_state(
+            Arc::<str>::from(token),
+            require_admin_token,
+        ));
+    Router::new().nest("/admin", admin).with_state(registry)
+}
+
+async fn healthz() -> &'static str {
+    "ok"
+}
+
+/// Ready while the registry answers; it stops answering once a shutdown begins.
+async fn readyz(State(registry): State<RegistryAddr>) -> impl IntoResponse {
+    match query_status(&registry).await {
+        Some(_) => (StatusCode::OK, "ready"),
+        None => (StatusCode::SERVICE_UNAVAILABLE, "not ready"),
+    }
+}
+
+async fn status(State(registry): State<RegistryAddr>) -> Result<Json<ServerStatus>, StatusCode> {
+    query_status(&registry)
+        .await
+        .map(Json)
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)
+}
+
+async fn metrics() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        METRICS.render(),
+    )
+}
+
+async fn require_admin_token(
+    State(token): State<Arc<str>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if is_authorized(request.headers(), &token) {
+        next.run(request).await
+    } else {
+        StatusCode::UNAUTHORIZED.into_response()
+    }
+}
+
+/// Checks for `Authorization: Bearer <token>`, comparing in constant time.
+fn is_authorized(headers: &HeaderMap, token: &str) -> bool {
+    let Some(presented) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+    presented.len() == token.len()
+        && presented
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Every running room with its current state; rooms that do not answer in time are left out.
+async fn list_rooms(
+    State(registry): State<RegistryAddr>,
+) -> Result<Json<Vec<RoomState>>, StatusCode> {
+    let rooms = ask(&registry, RegistryMessage::ListRooms)
+        .await
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let mut states = Vec::with_capacity(rooms.len());
+    for (_code, control) in rooms {
+        let (reply_sdr, reply_rcr) = oneshot::channel();
+        let state = timeout(REGISTRY_TIMEOUT, async {
+            control.send(RoomControl::Inspect(reply_sdr)).await.ok()?;
+            reply_rcr.await.ok()
+        })
+        .await;
+        if let Ok(Some(state)) = state {
+            states.push(state);
+        }
+    }
+    states.sort_by(|a, b| a.code.cmp(&b.code));
+    Ok(Json(states))
+}
+
+async fn list_sessions(
+    State(registry): State<RegistryAddr>,
+) -> Result<Json<Vec<SessionSummary>>, StatusCode> {
+    ask(&registry, RegistryMessage::ListSessions)
+        .await
+        .map(Json)
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)
+}
+
+async fn close_room(State(registry): State<RegistryAddr>, Path(code): Path<String>) -> StatusCode {
+    let closed = ask(&registry, |reply| RegistryMessage::CloseRoom {
+        code,
+        reason: ADMIN_REASON.to_string(),
+        reply,
+    })
+    .await;
+    found_status(closed)
+}
+
+async fn disconnect_session(
+    State(registry): State<RegistryAddr>,
+    Path(id): Path<String>,
+) -> StatusCode {
+    let disconnected = ask(&registry, |reply| RegistryMessage::DisconnectSession {
+        id,
+        reason: ADMIN_REASON.to_string(),
+        reply,
+    })
+    .await;
+    found_status(disconnected)
+}
+
+#[derive(Deserialize)]
+struct Announcement {
+    text: String,
+}
+
+#[derive(Serialize)]
+struct AnnouncementSent {
+    delivered: usize,
+}
+
+async fn announce(
+    State(registry): State<RegistryAddr>,
+    Json(announcement): Json<Announcement>,
+) -> Result<Json<AnnouncementSent>, StatusCode> {
+    let text = announcement.text.trim().to_string();
+    if text.is_empty() {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    ask(&registry, |reply| RegistryMessage::Announce { text, reply })
+        .await
+        .map(|delivered| Json(AnnouncementSent { delivered }))
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)
+}
+
+fn found_status(found: Option<bool>) -> StatusCode {
+    match found {
+        Some(true) => StatusCode::NO_CONTENT,
+        Some(false) => StatusCode::NOT_FOUND,
+        None => StatusCode::SERVICE_UNAVAILABLE,
+    }
+}
+
+async fn query_status(registry: &RegistryAddr) -> Option<ServerStatus> {
+    ask(registry, RegistryMessage::QueryStatus).await
+}
+
+/// Sends the registry a request and waits for its reply, giving up after [`REGISTRY_TIMEOUT`].
+async fn ask<T>(
+    registry: &RegistryAddr,
+    message: impl FnOnce(Sender<T>) -> RegistryMessage,
+) -> Option<T> {
+    let (reply_sdr, reply_rcr) = oneshot::channel();
+    timeout(REGISTRY_TIMEOUT, async {
+        registry.send(message(reply_sdr)).await.ok()?;
+        reply_rcr.await.ok()
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{HeaderMap, HeaderValue, header};
+
+    use super::is_authorized;
+
+    #[test]
+    fn admin_requests_need_the_exact_bearer_token() {
+        let mut headers = HeaderMap::new();
+        assert!(!is_authorized(&headers, "secret"));
+
+        for (value, authorized) in [
+            ("Bearer secret", true),
+            ("Bearer secreT", false),
+            ("Bearer secret2", false),
+            ("Basic secret", false),
+            ("secret", false),
+        ] {
+            headers.insert(header::AUTHORIZATION, HeaderValue::from_static(value));
+            assert_eq!(is_authorized(&headers, "secret"), authorized, "{value}");
+        }
+    }
+}
@@ -1,4 +1,15 @@
+pub mod accounts;
+pub mod chat;
+pub mod config;
+pub mod http;
+pub mod matchmaking;
+pub mod metrics;
+pub mod persistence;
 pub mod protocol;
+pub mod ratelimit;
 pub mod registry;
 pub mod room;
 pub mod session;
+pub mod shutdown;
+pub mod stats;
+pub mod tls;
@@ -1,82 +1,237 @@
-use std::println;
+use std::{net::SocketAddr, println, time::Duration};
 
 use anyhow::Result;
-use clap::Parser;
 use tokio::{
     net::{TcpListener, TcpStream},
-    sync::oneshot,
+    signal,
+    sync::{oneshot, watch},
+    time::{Instant, timeout_at},
+};
+use tokio_rustls::TlsAcceptor;
+use tokio_tungstenite::{
+    accept_hdr_async,
+    tungstenite::{
+        handshake::server::{Request, Response},
+        http::{HeaderValue, header::SEC_WEBSOCKET_PROTOCOL},
+    },
 };
-use tokio_tungstenite::accept_async;
 use tracing::{Instrument, info, warn};
 use tracing_subscriber::EnvFilter;
 
 use multisweeper_server::{
-    protocol::registry::RegistryMessage,
+    accounts::AccountStore,
+    config::{Config, LogFormat},
+    http,
+    persistence::RoomStore,
+    protocol::{registry::RegistryMessage, wire::WireEncoding},
     registry::{Registry, RegistryAddr},
-    session::{PlayerId, Session},
+    session::{Heartbeat, PlayerId, Session},
+    shutdown::{ShutdownNotice, ShutdownSender, ShutdownSignal},
+    stats::StatsStore,
+    tls::{self, ClientConnection},
 };
 
-#[derive(Parser, Debug)]
-#[command(version, about, long_about=None)]
-struct Args {
-    #[clap(
-        long,
-        default_value("8080"),
-        help(
-            "Set the port to expose the server on, overrides the $SERVER_PORT environment variable (default: 8080)"
-        )
-    )]
-    port: u16,
-}
-
-struct Config {
-    port: u16,
-}
-
-fn read_config() -> Result<Config> {
-    let args = Args::parse();
-    Ok(Config { port: args.port })
-}
+/// How long a new connection has to finish its TLS and WebSocket handshakes.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[tracing::instrument]
 #[tokio::main]
 async fn main() -> Result<()> {
-    init_tracing();
-    let config = read_config()?;
-    let registry = Registry::new();
+    let config = Config::load()?;
+    init_tracing(config.log_format);
+    let accounts = match &config.accounts_file {
+        Some(path) => AccountStore::open(path)?,
+        None => AccountStore::in_memory(),
+    };
+    let stats = match &config.stats_file {
+        Some(path) => StatsStore::open(path)?,
+        None => StatsStore::in_memory(),
+    };
+    let mut registry = Registry::new()
+        .with_mailbox_capacity(config.mailbox_capacity)
+        .with_room_limits(config.room_limits)
+        .with_account_store(accounts)
+        .with_stats_store(stats);
+    if let Some(dir) = &config.rooms_dir {
+        registry = registry.with_room_store(RoomStore::new(dir));
+    }
     let registry_addr = registry.request_addr();
     tokio::spawn(registry.handle_connections());
 
-    let listener = TcpListener::bind(format!("0.0.0.0:{}", config.port)).await?;
+    let http_address = SocketAddr::new(config.bind_address, config.http_port);
+    let http_listener = TcpListener::bind(http_address).await?;
+    info!("serving health and status endpoints on {http_address}");
+    let http_router = http::router(registry_addr.clone());
+    tokio::spawn(async move {
+        if let Err(error) = axum::serve(http_listener, http_router).await {
+            warn!(error = %error, "HTTP endpoint server failed");
+        }
+    });
+
+    if let Some(token) = &config.admin_token {
+        let admin_address = SocketAddr::new(config.admin_bind_address, config.admin_port);
+        let admin_listener = TcpListener::bind(admin_address).await?;
+        info!("serving admin endpoints under /admin on {admin_address}");
+        if !admin_address.ip().is_loopback() {
+            warn!("admin endpoints are reachable beyond this host over plain HTTP");
+        }
+        let admin_router = http::admin_router(registry_addr.clone(), token);
+        tokio::spawn(async move {
+            if let Err(error) = axum::serve(admin_listener, admin_router).await {
+                warn!(error = %error, "admin endpoint server failed");
+            }
+        });
+    }
 
-    println!("server is live and listening on port {}", config.port);
-    info!("listening on port {}", config.port);
+    let tls = match &config.tls {
+        Some((cert, key)) => Some(tls::load_acceptor(cert, key)?),
+        None => None,
+    };
+    let scheme = if tls.is_some() { "wss" } else { "ws" };
+    let bind_address = SocketAddr::new(config.bind_address, config.port);
+    let listener = TcpListener::bind(bind_address).await?;
 
-    while let Ok((stream, addr)) = listener.accept().await {
+    println!("server is live and listening on {bind_address} ({scheme})");
+    info!("listening on {bind_address} ({scheme})");
+
+    let (shutdown, _) = watch::channel(None);
+    let stop = shutdown_signal();
+    tokio::pin!(stop);
+    loop {
+        let (stream, addr) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(error) => {
+                    warn!(error = %error, "listener failed");
+                    break;
+                }
+            },
+            _ = &mut stop => break,
+        };
         let span = tracing::info_span!(
             "connection.accept",
             peer_address = %addr,
         );
-        tokio::spawn(accept_connection(stream, registry_addr.clone()).instrument(span));
+        tokio::spawn(
+            accept_connection(
+                stream,
+                tls.clone(),
+                registry_addr.clone(),
+                config.heartbeat,
+                config.mailbox_capacity,
+                shutdown.subscribe(),
+            )
+            .instrument(span),
+        );
     }
+    drop(listener);
 
     warn!("terminating server");
+    let notice = ShutdownNotice {
+        reason: "server is shutting down".to_string(),
+        restart_in_secs: config.restart_eta_secs,
+    };
+    drain(registry_addr, shutdown, notice, config.shutdown_timeout).await;
 
     Ok(())
 }
 
-fn init_tracing() {
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate = signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("failed to install the SIGTERM handler");
+        tokio::select! {
+            _ = signal::ctrl_c() => (),
+            _ = terminate.recv() => (),
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = signal::ctrl_c().await;
+}
+
+/// Stops the rooms first so their snapshots are kept, then tells every session to close,
+/// giving up on whatever is still running once `timeout` has passed.
+async fn drain(
+    registry: RegistryAddr,
+    sessions: ShutdownSender,
+    notice: ShutdownNotice,
+    timeout: Duration,
+) {
+    let deadline = Instant::now() + timeout;
+    let (reply_sdr, reply_rcr) = oneshot::channel::<()>();
+    let rooms_stopped = registry
+        .send(RegistryMessage::Shutdown {
+            notice: notice.clone(),
+            reply: reply_sdr,
+        })
+        .await
+        .is_ok()
+        && matches!(timeout_at(deadline, reply_rcr).await, Ok(Ok(())));
+    if !rooms_stopped {
+        warn!(
+            target: "multisweeper.server.shutdown_incomplete",
+            "rooms did not stop before the shutdown deadline"
+        );
+    }
+
+    sessions.send_replace(Some(notice));
+    if timeout_at(deadline, sessions.closed()).await.is_err() {
+        warn!(
+            target: "multisweeper.server.shutdown_incomplete",
+            session_count = sessions.receiver_count(),
+            "sessions did not close before the shutdown deadline"
+        );
+    }
+    info!(target: "multisweeper.server.shutdown", "server shut down");
+}
+
+fn init_tracing(format: LogFormat) {
     let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
-    tracing_subscriber::fmt()
+    let subscriber = tracing_subscriber::fmt()
         .with_env_filter(filter)
         .with_target(true)
-        .with_thread_ids(true)
-        .init();
+        .with_thread_ids(true);
+    match format {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Compact => subscriber.compact().init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
 }
 
+// The handshake callback's error type is fixed by tungstenite.
+#[allow(clippy::result_large_err)]
 #[tracing::instrument(name = "connection.handshake", skip_all)]
-async fn accept_connection(stream: TcpStream, registry: RegistryAddr) -> Result<()> {
-    let ws_stream = accept_async(stream).await?;
+async fn accept_connection(
+    stream: TcpStream,
+    tls: Option<TlsAcceptor>,
+    registry: RegistryAddr,
+    heartbeat: Heartbeat,
+    mailbox_capacity: usize,
+    shutdown: ShutdownSignal,
+) -> Result<()> {
+    let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+    let stream: ClientConnection = match tls {
+        Some(acceptor) => Box::new(timeout_at(deadline, acceptor.accept(stream)).await??),
+        None => Box::new(stream),
+    };
+    let mut encoding = WireEncoding::default();
+    let handshake = accept_hdr_async(stream, |request: &Request, mut response: Response| {
+        if let Some(chosen) = request
+            .headers()
+            .get(SEC_WEBSOCKET_PROTOCOL)
+            .and_then(|offered| offered.to_str().ok())
+            .and_then(WireEncoding::from_subprotocols)
+        {
+            encoding = chosen;
+            response.headers_mut().insert(
+                SEC_WEBSOCKET_PROTOCOL,
+                HeaderValue::from_static(chosen.subprotocol()),
+            );
+        }
+        Ok(response)
+    });
+    let ws_stream = timeout_at(deadline, handshake).await??;
     let (reply_sdr, reply_rcr) = oneshot::channel::<PlayerId>();
     registry
         .send(RegistryMessage::CreatePlayer(reply_sdr))
@@ -86,9 +241,14 @@ async fn accept_connection(stream: TcpStream, registry: RegistryAddr) -> Result<
     info!(
         target: "multisweeper.session.created",
         player_id = %id,
+        encoding = ?encoding,
         "session created"
     );
-    let session = Session::new(id, ws_stream, registry);
+    let session = Session::new(id, ws_stream, registry)
+        .with_encoding(encoding)
+        .with_mailbox_capacity(mailbox_capacity)
+        .with_heartbeat(heartbeat)
+        .with_shutdown(shutdown);
     tokio::spawn(session.handle_connections());
     Ok(())
 }
@@ -0,0 +1,181 @@
+use std::{collections::HashMap, time::Duration};
+
+use tokio::time::Instant;
+
+use crate::{
+    protocol::{session::MessageId, wire::ClientDifficulty},
+    session::{PlayerAddr, PlayerId},
+    stats::MatchMode,
+};
+
+/// Players needed to form a room straight away, unless rooms hold fewer.
+pub const MATCH_SIZE: usize = 4;
+/// Players needed to form a room once a queue has waited [`QUEUE_TIMEOUT`].
+pub const MIN_MATCH_SIZE: usize = 2;
+pub const QUEUE_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub type QueueKey = (MatchMode, ClientDifficulty);
+
+pub struct QueuedPlayer {
+    pub id: PlayerId,
+    pub addr: PlayerAddr,
+    /// The `matchmaking.join` request, so the eventual match can be correlated to it.
+    pub request_id: MessageId,
+}
+
+struct Queue {
+    players: Vec<QueuedPlayer>,
+    deadline: Instant,
+}
+
+/// Sessions waiting for quick play, grouped by the mode and difficulty they asked for.
+pub struct Matchmaker {
+    queues: HashMap<QueueKey, Queue>,
+    /// Players in a full group; never more than a room can hold.
+    match_size: usize,
+}
+
+impl Matchmaker {
+    pub fn new() -> Self {
+        Matchmaker {
+            queues: HashMap::new(),
+            match_size: MATCH_SIZE,
+        }
+    }
+
+    /// Keeps groups small enough to fit in rooms of `max_players`.
+    pub fn with_max_players(mut self, max_players: usize) -> Self {
+        self.match_size = MATCH_SIZE.min(max_players);
+        self
+    }
+
+    /// Queues `player` for `key`, replacing any earlier request of theirs, and returns how many
+    /// players are now waiting for it.
+    pub fn join(&mut self, key: QueueKey, player: QueuedPlayer, now: Instant) -> usize {
+        self.leave(&player.id);
+        let queue = self.queues.entry(key).or_insert_with(|| Queue {
+            players: Vec::new(),
+            deadline: now + QUEUE_TIMEOUT,
+        });
+        queue.players.push(player);
+        queue.players.len()
+    }
+
+    pub fn leave(&mut self, id: &PlayerId) -> bool {
+        let mut removed = false;
+        for queue in self.queues.values_mut() {
+            let before = queue.players.len();
+            queue.players.retain(|player| &player.id != id);
+            removed |= queue.players.len() != before;
+        }
+        self.queues.retain(|_, queue| !queue.players.is_empty());
+        removed
+    }
+
+    pub fn queued(&self) -> usize {
+        self.queues.values().map(|queue| queue.players.len()).sum()
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.queues.values().map(|queue| queue.deadline).min()
+    }
+
+    /// Removes and returns the groups that should get a room: full queues, and queues past their
+    /// deadline with enough players. A lone player past the deadline keeps waiting.
+    pub fn take_ready(&mut self, now: Instant) -> Vec<(QueueKey, Vec<QueuedPlayer>)> {
+        let mut groups = Vec::new();
+        for (key, queue) in &mut self.queues {
+            queue.players.retain(|player| !player.addr.is_closed());
+            while queue.players.len() >= self.match_size {
+                groups.push((*key, queue.players.drain(..self.match_size).collect()));
+                queue.deadline = now + QUEUE_TIMEOUT;
+            }
+            if queue.deadline <= now {
+                if queue.players.len() >= MIN_MATCH_SIZE {
+                    groups.push((*key, std::mem::take(&mut queue.players)));
+                }
+                queue.deadline = now + QUEUE_TIMEOUT;
+            }
+        }
+        self.queues.retain(|_, queue| !queue.players.is_empty());
+        groups
+    }
+}
+
+impl Default for Matchmaker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{sync::mpsc, time::Instant};
+
+    use crate::{protocol::wire::ClientDifficulty, stats::MatchMode};
+
+    use super::{MATCH_SIZE, Matchmaker, QUEUE_TIMEOUT, QueuedPlayer};
+
+    #[test]
+    fn queues_fill_up_or_time_out() {
+        let (addr, _mailbox) = mpsc::channel(10);
+        let player = |index: usize| QueuedPlayer {
+            id: format!("P{index:05}"),
+            addr: addr.clone(),
+            request_id: format!("req-{index}"),
+        };
+        let easy = (MatchMode::TurnBased, ClientDifficulty::Easy);
+        let hard = (MatchMode::TurnBased, ClientDifficulty::Hard);
+        let now = Instant::now();
+        let mut matchmaker = Matchmaker::new();
+
+        for index in 0..MATCH_SIZE - 1 {
+            matchmaker.join(easy, player(index), now);
+        }
+        matchmaker.join(hard, player(10), now);
+        assert!(matchmaker.take_ready(now).is_empty());
+
+        matchmaker.join(easy, player(MATCH_SIZE), now);
+        let groups = matchmaker.take_ready(now);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].0, easy);
+        assert_eq!(groups[0].1.len(), MATCH_SIZE);
+
+        assert!(matchmaker.take_ready(now + QUEUE_TIMEOUT).is_empty());
+        matchmaker.join(hard, player(11), now);
+        assert!(matchmaker.leave(&"P00011".to_string()));
+        matchmaker.join(hard, player(12), now);
+        let groups = matchmaker.take_ready(now + QUEUE_TIMEOUT * 2);
+        let ids = groups[0]
+            .1
+            .iter()
+            .map(|p| p.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["P00010", "P00012"]);
+        assert!(matchmaker.next_deadline().is_none());
+    }
+
+    #[test]
+    fn groups_never_outgrow_the_rooms() {
+        let (addr, _mailbox) = mpsc::channel(10);
+        let easy = (MatchMode::TurnBased, ClientDifficulty::Easy);
+        let now = Instant::now();
+        let mut matchmaker = Matchmaker::new().with_max_players(3);
+
+        for index in 0..7 {
+            let player = QueuedPlayer {
+                id: format!("P{index:05}"),
+                addr: addr.clone(),
+                request_id: format!("req-{index}"),
+            };
+            matchmaker.join(easy, player, now);
+        }
+        let sizes: Vec<usize> = matchmaker
+            .take_ready(now)
+            .iter()
+            .map(|(_, players)| players.len())
+            .collect();
+        assert_eq!(sizes, [3, 3]);
+        assert_eq!(matchmaker.queued(), 1);
+    }
+}
@@ -0,0 +1,121 @@
+use std::sync::LazyLock;
+
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+
+use crate::{protocol::session::ErrorCode, stats::MatchOutcome};
+
+/// Process-wide metrics, exported in the Prometheus text format on `/metrics`.
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+pub struct Metrics {
+    registry: Registry,
+    pub sessions: IntGauge,
+    pub rooms: IntGauge,
+    pub matches_started: IntCounter,
+    matches_finished: IntCounterVec,
+    pub game_actions: IntCounter,
+    rejected_commands: IntCounterVec,
+    /// How long room commands wait in the room's mailbox before being handled.
+    pub room_mailbox_latency: Histogram,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("multisweeper".to_string()), None)
+            .expect("metric prefix is valid");
+        let metrics = Metrics {
+            sessions: IntGauge::new("sessions", "Connected WebSocket sessions")
+                .expect("metric is valid"),
+            rooms: IntGauge::new("rooms", "Running rooms").expect("metric is valid"),
+            matches_started: IntCounter::new("matches_started_total", "Matches started")
+                .expect("metric is valid"),
+            matches_finished: IntCounterVec::new(
+                Opts::new("matches_finished_total", "Matches finished, by outcome"),
+                &["outcome"],
+            )
+            .expect("metric is valid"),
+            game_actions: IntCounter::new("game_actions_total", "Game actions submitted")
+                .expect("metric is valid"),
+            rejected_commands: IntCounterVec::new(
+                Opts::new(
+                    "rejected_commands_total",
+                    "Client commands rejected, by error code",
+                ),
+                &["code"],
+            )
+            .expect("metric is valid"),
+            room_mailbox_latency: Histogram::with_opts(
+                HistogramOpts::new(
+                    "room_mailbox_latency_seconds",
+                    "Time room commands spend queued before being handled",
+                )
+                .buckets(vec![
+                    0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0,
+                ]),
+            )
+            .expect("metric is valid"),
+            registry,
+        };
+        for collector in [
+            Box::new(metrics.sessions.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(metrics.rooms.clone()),
+            Box::new(metrics.matches_started.clone()),
+            Box::new(metrics.matches_finished.clone()),
+            Box::new(metrics.game_actions.clone()),
+            Box::new(metrics.rejected_commands.clone()),
+            Box::new(metrics.room_mailbox_latency.clone()),
+        ] {
+            metrics
+                .registry
+                .register(collector)
+                .expect("metric names are unique");
+        }
+        metrics
+    }
+
+    pub fn match_finished(&self, outcome: MatchOutcome) {
+        let outcome = match outcome {
+            MatchOutcome::Won => "won",
+            MatchOutcome::NoWinner => "no_winner",
+        };
+        self.matches_finished.with_label_values(&[outcome]).inc();
+    }
+
+    pub fn command_rejected(&self, code: &ErrorCode) {
+        let code = serde_json::to_value(code)
+            .ok()
+            .and_then(|code| code.as_str().map(str::to_string))
+            .unwrap_or_default();
+        self.rejected_commands.with_label_values(&[code]).inc();
+    }
+
+    /// Renders every metric in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("text encoding into a buffer cannot fail");
+        String::from_utf8(buffer).expect("text encoding produces utf-8")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{protocol::session::ErrorCode, stats::MatchOutcome};
+
+    use super::METRICS;
+
+    #[test]
+    fn metrics_render_with_labels() {
+        METRICS.match_finished(MatchOutcome::NoWinner);
+        METRICS.command_rejected(&ErrorCode::RateLimited);
+
+        let rendered = METRICS.render();
+        assert!(rendered.contains("multisweeper_matches_finished_total{outcome=\"no_winner\"}"));
+        assert!(rendered.contains("multisweeper_rejected_commands_total{code=\"rate_limited\"}"));
+        assert!(rendered.contains("# TYPE multisweeper_room_mailbox_latency_seconds histogram"));
+    }
+}
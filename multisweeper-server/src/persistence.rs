@@ -0,0 +1,143 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use multisweeper_core::{GameMove, GameSnapshot};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::warn;
+
+use crate::{
+    accounts::Account,
+    protocol::{
+        session::{PlayerState, RoomSettings},
+        wire::ClientDifficulty,
+    },
+    room::RoomCode,
+    session::PlayerId,
+};
+
+#[derive(Debug, Error)]
+pub enum PersistenceError {
+    #[error("room store io failed: {0}")]
+    Io(#[from] io::Error),
+    #[error("room snapshot is corrupt: {0}")]
+    Corrupt(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeatSnapshot {
+    pub id: PlayerId,
+    pub account: Option<Account>,
+    pub state: PlayerState,
+}
+
+/// Everything needed to rebuild a match: the board comes back by replaying `moves` over `seed`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub difficulty: ClientDifficulty,
+    pub seed: u64,
+    pub moves: Vec<GameMove>,
+    pub participants: Vec<PlayerId>,
+    pub accounts: HashMap<PlayerId, Account>,
+    pub scores: HashMap<PlayerId, u32>,
+    pub last_player: Option<PlayerId>,
+    pub current_player: PlayerId,
+    #[serde(default)]
+    pub eliminated: Vec<PlayerId>,
+    #[serde(default)]
+    pub reveals_this_turn: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum MatchSnapshot {
+    Waiting,
+    Playing(GameRecord),
+    Won { final_snapshot: GameSnapshot },
+    NoWinner { final_snapshot: GameSnapshot },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomSnapshot {
+    pub code: RoomCode,
+    pub owner: Option<PlayerId>,
+    pub seats: Vec<SeatSnapshot>,
+    #[serde(default)]
+    pub settings: RoomSettings,
+    /// Player and account ids the owner has banned.
+    #[serde(default)]
+    pub banned: HashSet<String>,
+    pub match_state: MatchSnapshot,
+}
+
+/// A directory holding one JSON snapshot per live room.
+#[derive(Debug, Clone)]
+pub struct RoomStore {
+    dir: PathBuf,
+}
+
+impl RoomStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        RoomStore { dir: dir.into() }
+    }
+
+    fn path_for(&self, code: &RoomCode) -> PathBuf {
+        self.dir.join(format!("{code}.json"))
+    }
+
+    pub fn save(&self, code: &RoomCode, contents: &[u8]) -> Result<(), PersistenceError> {
+        write_atomically(&self.path_for(code), contents)?;
+        Ok(())
+    }
+
+    pub fn remove(&self, code: &RoomCode) -> Result<(), PersistenceError> {
+        match fs::remove_file(self.path_for(code)) {
+            Ok(()) => Ok(()),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Reads every snapshot in the directory, skipping (and logging) any that fail to parse.
+    pub fn load_all(&self) -> Result<Vec<RoomSnapshot>, PersistenceError> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error.into()),
+        };
+        let mut snapshots = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            match fs::read(&path)
+                .map_err(PersistenceError::from)
+                .and_then(|contents| Ok(serde_json::from_slice(&contents)?))
+            {
+                Ok(snapshot) => snapshots.push(snapshot),
+                Err(error) => warn!(
+                    target: "multisweeper.persistence.snapshot_skipped",
+                    path = %path.display(),
+                    error = %error,
+                    "room snapshot could not be loaded"
+                ),
+            }
+        }
+        Ok(snapshots)
+    }
+}
+
+pub(crate) fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)?;
+    }
+    let staging = path.with_extension("tmp");
+    fs::write(&staging, contents)?;
+    fs::rename(staging, path)
+}
@@ -1,7 +1,10 @@
-use asyncapi_rust::AsyncApi;
+use asyncapi_rust::{AsyncApi, MessageRef};
 
 use super::wire::{ClientRequest, ServerMessage};
 
+/// Where `generate_asyncapi` writes the spec, relative to the crate root.
+pub const ASYNCAPI_PATH: &str = "docs/asyncapi.json";
+
 #[derive(AsyncApi)]
 #[asyncapi(
     title = "Multisweeper WebSocket API",
@@ -20,3 +23,99 @@ use super::wire::{ClientRequest, ServerMessage};
 #[asyncapi_messages(ClientRequest, ServerMessage)]
 #[allow(clippy::duplicated_attributes)]
 pub struct MultisweeperApi;
+
+/// The AsyncAPI document for the wire protocol, as written to [`ASYNCAPI_PATH`].
+pub fn asyncapi_document() -> String {
+    let mut spec = MultisweeperApi::asyncapi_spec();
+
+    let client_message_names = ClientRequest::asyncapi_message_names();
+    let server_message_names = ServerMessage::asyncapi_message_names();
+
+    let channel_messages = client_message_names
+        .iter()
+        .chain(server_message_names.iter())
+        .map(|name| {
+            (
+                (*name).to_string(),
+                MessageRef::Reference {
+                    reference: format!("#/components/messages/{name}"),
+                },
+            )
+        })
+        .collect();
+
+    spec.channels
+        .as_mut()
+        .expect("generated channels")
+        .get_mut("multisweeper")
+        .expect("generated channel")
+        .messages = Some(channel_messages);
+
+    let references = |names: &[&'static str]| {
+        names
+            .iter()
+            .map(|name| MessageRef::Reference {
+                reference: format!("#/channels/multisweeper/messages/{name}"),
+            })
+            .collect()
+    };
+
+    let operations = spec.operations.as_mut().expect("generated operations");
+    operations
+        .get_mut("clientMessages")
+        .expect("client message operation")
+        .messages = Some(references(&client_message_names));
+    operations
+        .get_mut("serverMessages")
+        .expect("server message operation")
+        .messages = Some(references(&server_message_names));
+
+    let mut document =
+        serde_json::to_value(&spec).expect("failed to serialize AsyncAPI specification");
+    let correlation_id = serde_json::json!({
+        "description": "The message_id of the client command that caused this server message, when applicable.",
+        "location": "$message.payload#/correlation_id"
+    });
+    for message_name in [
+        "ConnectionPong",
+        "ConnectionWelcome",
+        "ConnectionEncodingSet",
+        "RoomsListed",
+        "RoomsSubscribed",
+        "RoomsUnsubscribed",
+        "RoomState",
+        "RoomRemoved",
+        "CommandRejected",
+        "GameStarted",
+        "PlayerIdentified",
+        "StatsReported",
+        "LeaderboardReported",
+        "MatchmakingQueued",
+        "MatchmakingLeft",
+        "MatchmakingMatched",
+        "ChatMessage",
+        "ChatHistory",
+    ] {
+        document["components"]["messages"][message_name]["correlationId"] = correlation_id.clone();
+    }
+    let spec = serde_json::to_string_pretty(&document)
+        .expect("failed to serialize AsyncAPI specification");
+    format!("{spec}\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::{ASYNCAPI_PATH, asyncapi_document};
+
+    #[test]
+    fn checked_in_spec_matches_the_wire_types() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(ASYNCAPI_PATH);
+        let checked_in = fs::read_to_string(path).expect("spec should be checked in");
+        assert!(
+            checked_in == asyncapi_document(),
+            "{ASYNCAPI_PATH} is stale; run `cargo run --bin generate_asyncapi`"
+        );
+    }
+}
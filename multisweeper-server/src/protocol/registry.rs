@@ -1,17 +1,96 @@
 use tokio::sync::oneshot::Sender;
 
 use crate::{
-    registry::RegistryError,
-    room::{RoomAddr, RoomCode},
-    session::PlayerId,
+    accounts::{AccountId, AccountToken, Identity},
+    matchmaking::{QueueKey, QueuedPlayer},
+    protocol::session::RoomSummary,
+    protocol::wire::ClientDifficulty,
+    registry::{RegistryError, RoomListQuery, RoomPage, ServerStatus, SessionSummary},
+    room::{RoomAddr, RoomCode, RoomControlAddr},
+    session::{PlayerAddr, PlayerId},
+    shutdown::ShutdownNotice,
+    stats::{LeaderboardEntry, MatchRecord, PlayerStats},
 };
 
 pub enum RegistryMessage {
-    CreateLobby(Sender<RoomAddr>),
+    CreateLobby(Sender<Result<RoomAddr, RegistryError>>),
     RequestLobby {
         code: RoomCode,
         reply: Sender<Result<RoomAddr, RegistryError>>,
     },
-    QueryLobbies(Sender<Vec<RoomCode>>),
+    QueryLobbies {
+        query: RoomListQuery,
+        reply: Sender<RoomPage>,
+    },
+    /// Sent by a room whenever its summary changes; `revision` orders updates sent concurrently.
+    RoomUpdated {
+        summary: RoomSummary,
+        revision: u64,
+    },
     CreatePlayer(Sender<PlayerId>),
+    /// Sent by a session once its connection is ready, so operators can reach it.
+    AttachSession {
+        id: PlayerId,
+        addr: PlayerAddr,
+    },
+    /// Sent by a session as it closes.
+    ReleasePlayer(PlayerId),
+    QueryStatus(Sender<ServerStatus>),
+    /// Starts pushing room changes to a session, replying with every public room.
+    SubscribeRooms {
+        id: PlayerId,
+        reply: Sender<Vec<RoomSummary>>,
+    },
+    UnsubscribeRooms(PlayerId),
+    /// Sent by a room after its task has ended.
+    RoomClosed(RoomCode),
+    ListSessions(Sender<Vec<SessionSummary>>),
+    ListRooms(Sender<Vec<(RoomCode, RoomControlAddr)>>),
+    /// Removes every player from a room and closes it, replying whether the room existed.
+    CloseRoom {
+        code: RoomCode,
+        reason: String,
+        reply: Sender<bool>,
+    },
+    /// Ends a session, replying whether it was connected.
+    DisconnectSession {
+        id: PlayerId,
+        reason: String,
+        reply: Sender<bool>,
+    },
+    /// Sends a message to every connected session, replying with how many it reached.
+    Announce {
+        text: String,
+        reply: Sender<usize>,
+    },
+    IdentifyPlayer {
+        id: PlayerId,
+        token: Option<AccountToken>,
+        display_name: Option<String>,
+        reply: Sender<Result<Identity, RegistryError>>,
+    },
+    RecordMatch(MatchRecord),
+    QueryStats {
+        account_id: AccountId,
+        reply: Sender<PlayerStats>,
+    },
+    QueryLeaderboard {
+        difficulty: ClientDifficulty,
+        limit: usize,
+        reply: Sender<Vec<LeaderboardEntry>>,
+    },
+    JoinMatchmaking {
+        key: QueueKey,
+        player: QueuedPlayer,
+        reply: Sender<usize>,
+    },
+    LeaveMatchmaking {
+        id: PlayerId,
+        reply: Sender<bool>,
+    },
+    /// Stops every room, replying once they have all persisted and exited.
+    Shutdown {
+        notice: ShutdownNotice,
+        reply: Sender<()>,
+    },
 }
@@ -1,14 +1,58 @@
 use multisweeper_core::{GameAction, GameDifficulty};
+use tokio::{sync::oneshot, time::Instant};
 
-use crate::protocol::session::MessageId;
+use crate::accounts::Account;
+use crate::protocol::session::{MessageId, TurnOrder, TurnPolicy};
+use crate::protocol::wire::ClientDifficulty;
+use crate::room::RoomState;
 use crate::session::{PlayerAddr, PlayerId};
 
 pub enum PlayerCommand {
-    Join,
+    Join {
+        account: Option<Account>,
+        /// Mark the player ready as soon as they are seated, as quick play does.
+        ready: bool,
+    },
+    Identify {
+        account: Account,
+    },
     Leave,
-    StartGame { difficulty: GameDifficulty },
-    GameAction { action: GameAction },
+    StartGame {
+        difficulty: GameDifficulty,
+    },
+    GameAction {
+        action: GameAction,
+    },
     GameQuery,
+    Chat {
+        text: String,
+    },
+    Configure {
+        changes: RoomSettingsUpdate,
+    },
+    Kick {
+        target: PlayerId,
+        ban: bool,
+    },
+    TransferOwner {
+        target: PlayerId,
+    },
+    Ready {
+        ready: bool,
+    },
+}
+
+/// A partial [`RoomSettings`](crate::protocol::session::RoomSettings) change; `None` keeps the current value.
+#[derive(Debug, Clone, Default)]
+pub struct RoomSettingsUpdate {
+    pub hide_flags_from_spectators: Option<bool>,
+    pub spectator_delay_secs: Option<u16>,
+    pub min_ready: Option<u8>,
+    pub auto_start_secs: Option<u16>,
+    pub auto_start_difficulty: Option<ClientDifficulty>,
+    pub turn_order: Option<TurnOrder>,
+    pub turn_policy: Option<TurnPolicy>,
+    pub private: Option<bool>,
 }
 
 #[derive(Clone)]
@@ -17,8 +61,19 @@ pub struct RequestContext {
     pub reply_to: PlayerAddr,
 }
 
+/// Operator requests a room handles outside of any player's session.
+pub enum RoomControl {
+    Inspect(oneshot::Sender<RoomState>),
+    /// Removes every player and closes the room.
+    Close {
+        reason: String,
+    },
+}
+
 pub struct RoomMessage {
     pub id: PlayerId,
     pub request: RequestContext,
     pub command: PlayerCommand,
+    /// When the session queued the message, to measure mailbox latency.
+    pub sent_at: Instant,
 }
@@ -1,15 +1,17 @@
 use asyncapi_rust::schemars::JsonSchema;
 use multisweeper_core::GameSnapshot;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    protocol::wire::ClientDifficulty,
     room::{RoomCode, RoomState},
     session::PlayerId,
+    stats::MatchMode,
 };
 
 pub type MessageId = String;
 
-#[derive(Debug, Serialize, Clone, JsonSchema, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema, PartialEq, Eq)]
 pub enum PlayerState {
     Spectator,
     Playing,
@@ -19,7 +21,9 @@ pub enum PlayerState {
 #[derive(Debug, Serialize, Clone, JsonSchema)]
 pub struct PlayerView {
     pub id: PlayerId,
+    pub display_name: Option<String>,
     pub state: PlayerState,
+    pub ready: bool,
 }
 
 #[derive(Debug, Serialize, Clone, JsonSchema, PartialEq, Eq)]
@@ -28,17 +32,110 @@ pub enum MatchState {
     Playing {
         last_player: Option<PlayerId>,
         current_player: PlayerId,
+        /// Players still in the match who play after `current_player`, in turn order.
+        turn_queue: Vec<PlayerId>,
     },
     Won,
     NoWinner,
 }
 
+#[derive(Debug, Serialize, Clone, JsonSchema, PartialEq, Eq)]
+pub struct ChatMessageView {
+    pub sender: PlayerId,
+    pub display_name: Option<String>,
+    pub text: String,
+    /// Unix time in milliseconds.
+    pub sent_at: u64,
+}
+
+/// How a match's participants are ordered for turns.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, JsonSchema, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TurnOrder {
+    /// The order players took their seats in the room.
+    #[default]
+    JoinOrder,
+    /// Shuffled using the match's board seed.
+    Random,
+    /// Players eliminated in the previous match go first, earliest eliminated first.
+    LoserFirst,
+}
+
+/// When a player's turn passes to the next player after a successful action. Mines still end
+/// the turn and the match still ends when the board is cleared.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, JsonSchema, PartialEq, Eq, Default)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TurnPolicy {
+    /// Every reveal or flag ends the turn.
+    #[default]
+    EveryAction,
+    /// Flags are free; any reveal ends the turn.
+    FreeFlags,
+    /// Flags are free; the turn ends after `per_turn` reveals.
+    Reveals { per_turn: u8 },
+    /// Flags are free; the turn ends once a reveal uncovers a numbered cell.
+    UntilNumber,
+}
+
+/// Owner-adjustable options that shape how a room runs.
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct RoomSettings {
+    /// Show flags as hidden cells to anyone watching an in-progress match.
+    pub hide_flags_from_spectators: bool,
+    /// Seconds the spectator feed lags behind an in-progress match.
+    pub spectator_delay_secs: u16,
+    /// Ready players needed before a match can start; 0 means everyone in the room.
+    pub min_ready: u8,
+    /// Seconds to count down before starting automatically once enough players are ready;
+    /// 0 leaves starting to the owner.
+    pub auto_start_secs: u16,
+    pub auto_start_difficulty: ClientDifficulty,
+    pub turn_order: TurnOrder,
+    pub turn_policy: TurnPolicy,
+    /// Keep the room out of `rooms.list`; players join it by code.
+    pub private: bool,
+}
+
 #[derive(Debug, Serialize, Clone, JsonSchema)]
 pub struct MatchView {
     pub state: MatchState,
     pub game: Option<GameSnapshot>,
 }
 
+/// Where a room is in its match cycle, as shown in the lobby browser.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchPhase {
+    Waiting,
+    Playing,
+    Finished,
+}
+
+/// How a listed room changed in a `rooms.updated` event.
+#[derive(Debug, Serialize, Clone, Copy, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RoomChange {
+    Added,
+    Changed,
+    Removed,
+}
+
+/// What the lobby browser shows about a room; rooms keep the registry's copy current.
+#[derive(Debug, Serialize, Clone, JsonSchema, PartialEq, Eq)]
+pub struct RoomSummary {
+    pub code: RoomCode,
+    /// Seated players and spectators, including seats held for disconnected players.
+    pub player_count: usize,
+    pub capacity: usize,
+    pub owner_name: Option<String>,
+    pub mode: MatchMode,
+    /// The running match's difficulty, or the room's auto-start difficulty between matches.
+    pub difficulty: ClientDifficulty,
+    pub match_state: MatchPhase,
+    pub private: bool,
+}
+
 #[derive(Debug, Serialize, Clone, JsonSchema, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum ErrorCode {
@@ -59,6 +156,22 @@ pub enum ErrorCode {
     RoomUnavailable,
     InvalidMessage,
     DuplicateMessageId,
+    InvalidDisplayName,
+    AccountNotFound,
+    NotIdentified,
+    InvalidChatMessage,
+    RateLimited,
+    InvalidSettings,
+    PlayersNotReady,
+    BannedFromRoom,
+    CannotTargetSelf,
+    NotQueued,
+    RoomFull,
+    ServerFull,
+    /// The account store could not be written, so no account was created.
+    AccountUnavailable,
+    /// The client's `connection.hello` named a protocol version this server cannot speak.
+    UnsupportedProtocolVersion,
 }
 
 #[derive(Debug, Serialize, Clone, JsonSchema, PartialEq, Eq)]
@@ -91,6 +204,7 @@ pub enum SessionEvent {
         code: RoomCode,
         owner: Option<PlayerId>,
         players: Vec<PlayerView>,
+        settings: RoomSettings,
         game: MatchView,
     },
     RoomRemoved {
@@ -103,6 +217,30 @@ pub enum SessionEvent {
         error: ClientError,
     },
     GameStarted,
+    CountdownStarted {
+        starts_in_secs: u16,
+    },
+    CountdownCancelled,
+    MatchFound {
+        room_code: RoomCode,
+    },
+    ChatMessage(ChatMessageView),
+    ChatHistory {
+        messages: Vec<ChatMessageView>,
+    },
+    Announcement {
+        text: String,
+    },
+    /// A public room was added, changed or removed; sent to sessions browsing rooms.
+    RoomsUpdated {
+        change: RoomChange,
+        code: RoomCode,
+        room: Option<RoomSummary>,
+    },
+    /// The server is closing this connection; the session ends once it is delivered.
+    Disconnected {
+        reason: String,
+    },
 }
 
 impl From<RoomState> for SessionEvent {
@@ -111,6 +249,7 @@ impl From<RoomState> for SessionEvent {
             code: value.code,
             owner: value.owner,
             players: value.players,
+            settings: value.settings,
             game: value.match_state,
         }
     }
@@ -1,21 +1,70 @@
 use asyncapi_rust::{ToAsyncApiMessage, schemars::JsonSchema};
-use multisweeper_core::{GameAction, GameDifficulty};
+use multisweeper_core::{
+    GameAction, GameActionResult, GameCell, GameDifficulty, GameSnapshot, GameStatus,
+};
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 use tokio_tungstenite::tungstenite::Message;
 
 use crate::{
-    protocol::session::{ClientError, MessageId, SessionEvent, SessionMessage},
+    accounts::{AccountId, AccountToken},
+    protocol::session::{
+        ChatMessageView, ClientError, MatchPhase, MatchState, MatchView, MessageId, RoomChange,
+        RoomSettings, RoomSummary, SessionEvent, SessionMessage, TurnOrder, TurnPolicy,
+    },
     room::RoomCode,
     session::PlayerId,
+    stats::{LeaderboardEntry, MatchMode, PlayerStats},
 };
 
 #[derive(Debug, Deserialize, Serialize, JsonSchema, ToAsyncApiMessage)]
 #[serde(tag = "type")]
 pub enum ClientRequest {
+    /// Optional first message; the server checks the version and answers with its own.
+    #[serde(rename = "connection.hello")]
+    ConnectionHello {
+        message_id: MessageId,
+        protocol_version: u32,
+        /// Free-form client name and build, for logs.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        client: Option<String>,
+    },
     #[serde(rename = "connection.ping")]
     ConnectionPing { message_id: MessageId },
+    /// Switches the connection's encoding; the acknowledgment is the first message sent in it.
+    #[serde(rename = "connection.encoding")]
+    ConnectionEncoding {
+        message_id: MessageId,
+        encoding: WireEncoding,
+        /// Omit to keep the current board encoding.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        board: Option<BoardEncoding>,
+    },
+    /// Lists public rooms matching every given filter, a page at a time.
     #[serde(rename = "rooms.list")]
-    RoomsList { message_id: MessageId },
+    RoomsList {
+        message_id: MessageId,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        difficulty: Option<ClientDifficulty>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mode: Option<MatchMode>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        match_state: Option<MatchPhase>,
+        /// Only rooms with a free seat.
+        #[serde(default)]
+        open_only: bool,
+        /// Matching rooms to skip, ordered by room code.
+        #[serde(default)]
+        offset: usize,
+        /// Rooms to return; defaults to 20 and is capped at 100.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        limit: Option<usize>,
+    },
+    /// Pushes `rooms.updated` events while not in a room, until `rooms.unsubscribe`.
+    #[serde(rename = "rooms.subscribe")]
+    RoomsSubscribe { message_id: MessageId },
+    #[serde(rename = "rooms.unsubscribe")]
+    RoomsUnsubscribe { message_id: MessageId },
     #[serde(rename = "room.join")]
     RoomJoin {
         message_id: MessageId,
@@ -39,6 +88,75 @@ pub enum ClientRequest {
     },
     #[serde(rename = "room.state.get")]
     RoomStateGet { message_id: MessageId },
+    #[serde(rename = "player.identify")]
+    PlayerIdentify {
+        message_id: MessageId,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        display_name: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<AccountToken>,
+    },
+    #[serde(rename = "stats.get")]
+    StatsGet {
+        message_id: MessageId,
+        /// Defaults to the account this connection identified as.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        account_id: Option<AccountId>,
+    },
+    #[serde(rename = "leaderboard.get")]
+    LeaderboardGet {
+        message_id: MessageId,
+        difficulty: ClientDifficulty,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        limit: Option<u16>,
+    },
+    #[serde(rename = "chat.send")]
+    ChatSend { message_id: MessageId, text: String },
+    /// Owner only; omitted settings keep their current value.
+    #[serde(rename = "room.configure")]
+    RoomConfigure {
+        message_id: MessageId,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        hide_flags_from_spectators: Option<bool>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        spectator_delay_secs: Option<u16>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min_ready: Option<u8>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        auto_start_secs: Option<u16>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        auto_start_difficulty: Option<ClientDifficulty>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        turn_order: Option<TurnOrder>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        turn_policy: Option<TurnPolicy>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        private: Option<bool>,
+    },
+    #[serde(rename = "matchmaking.join")]
+    MatchmakingJoin {
+        message_id: MessageId,
+        difficulty: ClientDifficulty,
+        #[serde(default)]
+        mode: MatchMode,
+    },
+    #[serde(rename = "matchmaking.leave")]
+    MatchmakingLeave { message_id: MessageId },
+    #[serde(rename = "room.ready")]
+    RoomReady { message_id: MessageId, ready: bool },
+    /// Owner only; `ban` also keeps the player, and their account if any, from rejoining.
+    #[serde(rename = "room.kick")]
+    RoomKick {
+        message_id: MessageId,
+        player_id: PlayerId,
+        #[serde(default)]
+        ban: bool,
+    },
+    #[serde(rename = "room.transfer_owner")]
+    RoomTransferOwner {
+        message_id: MessageId,
+        player_id: PlayerId,
+    },
 }
 
 #[derive(Debug, Deserialize, Serialize, JsonSchema)]
@@ -57,9 +175,10 @@ impl ClientGameAction {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum ClientDifficulty {
     Test,
+    #[default]
     Easy,
     Medium,
     Hard,
@@ -76,32 +195,171 @@ impl From<ClientDifficulty> for GameDifficulty {
     }
 }
 
-impl TryFrom<Message> for ClientRequest {
-    type Error = serde_json::Error;
+impl From<GameDifficulty> for ClientDifficulty {
+    fn from(value: GameDifficulty) -> Self {
+        match value {
+            GameDifficulty::TEST => ClientDifficulty::Test,
+            GameDifficulty::EASY => ClientDifficulty::Easy,
+            GameDifficulty::MEDIUM => ClientDifficulty::Medium,
+            GameDifficulty::HARD => ClientDifficulty::Hard,
+        }
+    }
+}
+
+/// The protocol version this server speaks, raised whenever a change breaks existing clients.
+pub const PROTOCOL_VERSION: u32 = 1;
+/// The oldest client protocol version the server still accepts.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// Optional parts of the protocol, advertised in `connection.welcome`.
+#[derive(Debug, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProtocolFeature {
+    /// `connection.encoding` and the `multisweeper.msgpack` subprotocol.
+    MessagePack,
+    CompactBoard,
+    RoomsSubscribe,
+    Matchmaking,
+    Chat,
+    Accounts,
+}
+
+impl ProtocolFeature {
+    pub const ALL: [ProtocolFeature; 6] = [
+        ProtocolFeature::MessagePack,
+        ProtocolFeature::CompactBoard,
+        ProtocolFeature::RoomsSubscribe,
+        ProtocolFeature::Matchmaking,
+        ProtocolFeature::Chat,
+        ProtocolFeature::Accounts,
+    ];
+}
+
+#[derive(Debug, Error)]
+pub enum WireError {
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+    #[error("{0}")]
+    MessagePackDecode(#[from] rmp_serde::decode::Error),
+    #[error("{0}")]
+    MessagePackEncode(#[from] rmp_serde::encode::Error),
+}
 
-    fn try_from(value: Message) -> Result<Self, Self::Error> {
-        serde_json::from_slice(&value.into_data())
+/// How messages are framed on a connection. Clients choose MessagePack with the
+/// `multisweeper.msgpack` WebSocket subprotocol or a `connection.encoding` request.
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum WireEncoding {
+    /// JSON in text frames.
+    #[default]
+    Json,
+    /// MessagePack in binary frames, with structs encoded as maps keyed by field name.
+    #[serde(rename = "msgpack")]
+    MessagePack,
+}
+
+impl WireEncoding {
+    pub const JSON_SUBPROTOCOL: &str = "multisweeper.json";
+    pub const MESSAGE_PACK_SUBPROTOCOL: &str = "multisweeper.msgpack";
+
+    /// Picks the first supported protocol from a `Sec-WebSocket-Protocol` header value.
+    pub fn from_subprotocols(offered: &str) -> Option<Self> {
+        offered
+            .split(',')
+            .map(str::trim)
+            .find_map(|protocol| match protocol {
+                Self::JSON_SUBPROTOCOL => Some(WireEncoding::Json),
+                Self::MESSAGE_PACK_SUBPROTOCOL => Some(WireEncoding::MessagePack),
+                _ => None,
+            })
+    }
+
+    pub fn subprotocol(self) -> &'static str {
+        match self {
+            WireEncoding::Json => Self::JSON_SUBPROTOCOL,
+            WireEncoding::MessagePack => Self::MESSAGE_PACK_SUBPROTOCOL,
+        }
+    }
+
+    /// Text frames are always JSON; binary frames follow the connection's encoding.
+    pub fn decode(self, message: Message) -> Result<ClientRequest, WireError> {
+        match (self, message) {
+            (WireEncoding::MessagePack, Message::Binary(data)) => Ok(rmp_serde::from_slice(&data)?),
+            (_, message) => Ok(serde_json::from_slice(&message.into_data())?),
+        }
+    }
+
+    pub fn encode(self, message: &ServerMessage) -> Result<Message, WireError> {
+        match self {
+            WireEncoding::Json => Ok(Message::Text(serde_json::to_string(message)?.into())),
+            WireEncoding::MessagePack => {
+                Ok(Message::Binary(rmp_serde::to_vec_named(message)?.into()))
+            }
+        }
     }
 }
 
 #[derive(Debug, Serialize, JsonSchema, ToAsyncApiMessage)]
 #[serde(tag = "type")]
 pub enum ServerMessage {
+    /// The first message on every connection. Clients must check `protocol_version` and
+    /// disconnect if they do not speak it, or negotiate one with `connection.hello`.
     #[serde(rename = "connection.ready")]
     ConnectionReady {
         message_id: MessageId,
         player_id: PlayerId,
+        protocol_version: u32,
+    },
+    /// Accepts a `connection.hello`; an incompatible one is rejected with
+    /// `unsupported_protocol_version` and the connection is closed.
+    #[serde(rename = "connection.welcome")]
+    ConnectionWelcome {
+        message_id: MessageId,
+        correlation_id: MessageId,
+        protocol_version: u32,
+        min_protocol_version: u32,
+        features: Vec<ProtocolFeature>,
     },
     #[serde(rename = "connection.pong")]
     ConnectionPong {
         message_id: MessageId,
         correlation_id: MessageId,
     },
+    #[serde(rename = "connection.encoding_set")]
+    ConnectionEncodingSet {
+        message_id: MessageId,
+        correlation_id: MessageId,
+        encoding: WireEncoding,
+        board: BoardEncoding,
+    },
     #[serde(rename = "rooms.listed")]
     RoomsListed {
         message_id: MessageId,
         correlation_id: MessageId,
-        rooms: Vec<RoomCode>,
+        rooms: Vec<RoomSummary>,
+        /// Rooms matching the filters across all pages.
+        total: usize,
+    },
+    /// Every public room at the time of subscribing; later changes arrive as `rooms.updated`.
+    #[serde(rename = "rooms.subscribed")]
+    RoomsSubscribed {
+        message_id: MessageId,
+        correlation_id: MessageId,
+        rooms: Vec<RoomSummary>,
+    },
+    #[serde(rename = "rooms.unsubscribed")]
+    RoomsUnsubscribed {
+        message_id: MessageId,
+        correlation_id: MessageId,
+    },
+    #[serde(rename = "rooms.updated")]
+    RoomsUpdated {
+        message_id: MessageId,
+        change: RoomChange,
+        code: RoomCode,
+        /// The room's new summary; absent when it was removed.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        room: Option<RoomSummary>,
     },
     #[serde(rename = "room.state")]
     RoomState {
@@ -111,7 +369,12 @@ pub enum ServerMessage {
         code: RoomCode,
         owner: Option<PlayerId>,
         players: Vec<crate::protocol::session::PlayerView>,
-        game: crate::protocol::session::MatchView,
+        settings: RoomSettings,
+        game: WireMatchView,
+        /// The board on connections using the compact board encoding, which leave out
+        /// `game.game.board`.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        compact_board: Option<String>,
     },
     #[serde(rename = "room.removed")]
     RoomRemoved {
@@ -133,6 +396,88 @@ pub enum ServerMessage {
         #[serde(skip_serializing_if = "Option::is_none")]
         correlation_id: Option<MessageId>,
     },
+    #[serde(rename = "player.identified")]
+    PlayerIdentified {
+        message_id: MessageId,
+        correlation_id: MessageId,
+        player_id: PlayerId,
+        account_id: AccountId,
+        display_name: String,
+        token: AccountToken,
+    },
+    #[serde(rename = "stats.reported")]
+    StatsReported {
+        message_id: MessageId,
+        correlation_id: MessageId,
+        stats: PlayerStats,
+    },
+    #[serde(rename = "leaderboard.reported")]
+    LeaderboardReported {
+        message_id: MessageId,
+        correlation_id: MessageId,
+        difficulty: ClientDifficulty,
+        entries: Vec<LeaderboardEntry>,
+    },
+    #[serde(rename = "chat.message")]
+    ChatMessage {
+        message_id: MessageId,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        correlation_id: Option<MessageId>,
+        sender: PlayerId,
+        display_name: Option<String>,
+        text: String,
+        sent_at: u64,
+    },
+    #[serde(rename = "matchmaking.queued")]
+    MatchmakingQueued {
+        message_id: MessageId,
+        correlation_id: MessageId,
+        mode: MatchMode,
+        difficulty: ClientDifficulty,
+        /// Players waiting in this queue, including the requester.
+        queued: usize,
+    },
+    #[serde(rename = "matchmaking.left")]
+    MatchmakingLeft {
+        message_id: MessageId,
+        correlation_id: MessageId,
+    },
+    /// A room was formed from the queue; the server joins it on the player's behalf.
+    #[serde(rename = "matchmaking.matched")]
+    MatchmakingMatched {
+        message_id: MessageId,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        correlation_id: Option<MessageId>,
+        room_code: RoomCode,
+    },
+    #[serde(rename = "countdown.started")]
+    CountdownStarted {
+        message_id: MessageId,
+        starts_in_secs: u16,
+    },
+    #[serde(rename = "countdown.cancelled")]
+    CountdownCancelled { message_id: MessageId },
+    #[serde(rename = "server.announcement")]
+    ServerAnnouncement { message_id: MessageId, text: String },
+    #[serde(rename = "connection.closed")]
+    ConnectionClosed {
+        message_id: MessageId,
+        reason: String,
+    },
+    #[serde(rename = "server.shutdown")]
+    ServerShutdown {
+        message_id: MessageId,
+        reason: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        restart_in_secs: Option<u32>,
+    },
+    #[serde(rename = "chat.history")]
+    ChatHistory {
+        message_id: MessageId,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        correlation_id: Option<MessageId>,
+        messages: Vec<ChatMessageView>,
+    },
 }
 
 impl ServerMessage {
@@ -149,6 +494,7 @@ impl ServerMessage {
                 code,
                 owner,
                 players,
+                settings,
                 game,
             } => Self::RoomState {
                 message_id,
@@ -156,7 +502,9 @@ impl ServerMessage {
                 code,
                 owner,
                 players,
-                game,
+                settings,
+                game: game.into(),
+                compact_board: None,
             },
             SessionEvent::RoomRemoved { reason } => Self::RoomRemoved {
                 message_id,
@@ -174,29 +522,163 @@ impl ServerMessage {
                 message_id,
                 correlation_id,
             },
+            SessionEvent::ChatMessage(chat) => Self::ChatMessage {
+                message_id,
+                correlation_id,
+                sender: chat.sender,
+                display_name: chat.display_name,
+                text: chat.text,
+                sent_at: chat.sent_at,
+            },
+            SessionEvent::CountdownStarted { starts_in_secs } => Self::CountdownStarted {
+                message_id,
+                starts_in_secs,
+            },
+            SessionEvent::CountdownCancelled => Self::CountdownCancelled { message_id },
+            SessionEvent::MatchFound { room_code } => Self::MatchmakingMatched {
+                message_id,
+                correlation_id,
+                room_code,
+            },
+            SessionEvent::ChatHistory { messages } => Self::ChatHistory {
+                message_id,
+                correlation_id,
+                messages,
+            },
+            SessionEvent::Announcement { text } => Self::ServerAnnouncement { message_id, text },
+            SessionEvent::RoomsUpdated { change, code, room } => Self::RoomsUpdated {
+                message_id,
+                change,
+                code,
+                room,
+            },
+            SessionEvent::Disconnected { reason } => Self::ConnectionClosed { message_id, reason },
+        }
+    }
+}
+
+/// How boards in `room.state` are sent on a connection.
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BoardEncoding {
+    /// `game.game.board` as rows of cells.
+    #[default]
+    Cells,
+    /// `compact_board` as one character per cell, row by row from the top left: `#` hidden,
+    /// `F` flagged, `*` a revealed mine and `0`-`8` a revealed cell's adjacent mine count.
+    Compact,
+}
+
+impl BoardEncoding {
+    /// Moves the board of a `room.state` message into `compact_board` when compact.
+    pub fn apply(self, message: &mut ServerMessage) {
+        if self != BoardEncoding::Compact {
+            return;
+        }
+        if let ServerMessage::RoomState {
+            game,
+            compact_board,
+            ..
+        } = message
+            && let Some(GameView::Cells(snapshot)) = &game.game
+        {
+            *compact_board = Some(encode_compact_board(&snapshot.board));
+            game.game = Some(GameView::Boardless(snapshot.into()));
         }
     }
 }
 
-impl TryInto<Message> for ServerMessage {
-    type Error = serde_json::Error;
+/// The match in a `room.state` message.
+#[derive(Debug, Serialize, Clone, JsonSchema)]
+pub struct WireMatchView {
+    pub state: MatchState,
+    pub game: Option<GameView>,
+}
+
+impl From<MatchView> for WireMatchView {
+    fn from(view: MatchView) -> Self {
+        WireMatchView {
+            state: view.state,
+            game: view.game.map(GameView::Cells),
+        }
+    }
+}
+
+/// A game snapshot, without its board when the board is sent as `compact_board`.
+#[derive(Debug, Serialize, Clone, JsonSchema)]
+#[serde(untagged)]
+pub enum GameView {
+    Cells(GameSnapshot),
+    Boardless(BoardlessSnapshot),
+}
+
+/// Every field of a [`GameSnapshot`] except `board`.
+#[derive(Debug, Serialize, Clone, JsonSchema)]
+pub struct BoardlessSnapshot {
+    pub status: GameStatus,
+    pub action_result: GameActionResult,
+    pub elapsed_ms: u64,
+    pub width: u8,
+    pub height: u8,
+    pub mines: u8,
+    pub flags: u16,
+    pub mines_remaining: i16,
+    pub revealed: u16,
+}
 
-    fn try_into(self) -> Result<Message, Self::Error> {
-        let response = serde_json::to_string::<ServerMessage>(&self)?;
-        Ok(Message::Text(response.into()))
+impl From<&GameSnapshot> for BoardlessSnapshot {
+    fn from(snapshot: &GameSnapshot) -> Self {
+        BoardlessSnapshot {
+            status: snapshot.status,
+            action_result: snapshot.action_result.clone(),
+            elapsed_ms: snapshot.elapsed_ms,
+            width: snapshot.width,
+            height: snapshot.height,
+            mines: snapshot.mines,
+            flags: snapshot.flags,
+            mines_remaining: snapshot.mines_remaining,
+            revealed: snapshot.revealed,
+        }
     }
 }
 
+fn encode_compact_board(board: &[Vec<GameCell>]) -> String {
+    board
+        .iter()
+        .flatten()
+        .map(|cell| match cell {
+            GameCell::HiddenCell => '#',
+            GameCell::FlaggedCell => 'F',
+            GameCell::MinedCell => '*',
+            GameCell::VisibleCell(count) => char::from_digit((*count).into(), 10).unwrap_or('?'),
+        })
+        .collect()
+}
+
 impl ClientRequest {
     pub fn message_id(&self) -> &MessageId {
         match self {
             Self::ConnectionPing { message_id }
-            | Self::RoomsList { message_id }
+            | Self::ConnectionHello { message_id, .. }
+            | Self::ConnectionEncoding { message_id, .. }
+            | Self::RoomsList { message_id, .. }
             | Self::RoomCreate { message_id }
             | Self::RoomLeave { message_id }
             | Self::GameStart { message_id, .. }
             | Self::GameAction { message_id, .. }
             | Self::RoomStateGet { message_id }
+            | Self::PlayerIdentify { message_id, .. }
+            | Self::StatsGet { message_id, .. }
+            | Self::LeaderboardGet { message_id, .. }
+            | Self::ChatSend { message_id, .. }
+            | Self::RoomConfigure { message_id, .. }
+            | Self::RoomKick { message_id, .. }
+            | Self::RoomReady { message_id, .. }
+            | Self::MatchmakingJoin { message_id, .. }
+            | Self::MatchmakingLeave { message_id }
+            | Self::RoomsSubscribe { message_id }
+            | Self::RoomsUnsubscribe { message_id }
+            | Self::RoomTransferOwner { message_id, .. }
             | Self::RoomJoin { message_id, .. } => message_id,
         }
     }
@@ -204,9 +686,17 @@ impl ClientRequest {
 
 #[cfg(test)]
 mod tests {
-    use crate::protocol::session::{SessionEvent, SessionMessage};
+    use crate::protocol::session::{RoomChange, SessionEvent, SessionMessage};
 
-    use super::{ClientGameAction, ClientRequest, ServerMessage};
+    use multisweeper_core::{Game, GameCell, GameDifficulty};
+    use tokio_tungstenite::tungstenite::Message;
+
+    use crate::protocol::session::{MatchState, MatchView, RoomSettings};
+
+    use super::{
+        BoardEncoding, ClientGameAction, ClientRequest, ProtocolFeature, ServerMessage,
+        WireEncoding,
+    };
 
     #[test]
     fn client_messages_are_flat_and_correlatable() {
@@ -273,4 +763,132 @@ mod tests {
         assert_eq!(reply_json["correlation_id"], "req-1");
         assert!(broadcast_json.get("correlation_id").is_none());
     }
+
+    #[test]
+    fn removed_rooms_are_announced_without_a_summary() {
+        let removed = ServerMessage::from_session(
+            "m-1".to_string(),
+            SessionMessage::Broadcast(SessionEvent::RoomsUpdated {
+                change: RoomChange::Removed,
+                code: "quiet-otter".to_string(),
+                room: None,
+            }),
+        );
+
+        let json = serde_json::to_value(removed).expect("update should serialize");
+        assert_eq!(json["type"], "rooms.updated");
+        assert_eq!(json["change"], "removed");
+        assert_eq!(json["code"], "quiet-otter");
+        assert!(json.get("room").is_none());
+    }
+
+    #[test]
+    fn message_pack_connections_use_binary_frames() {
+        let encoding = WireEncoding::from_subprotocols("chat, multisweeper.msgpack")
+            .expect("msgpack should be recognised");
+        assert_eq!(encoding, WireEncoding::MessagePack);
+
+        let pong = ServerMessage::ConnectionPong {
+            message_id: "m-1".to_string(),
+            correlation_id: "req-1".to_string(),
+        };
+        let Message::Binary(data) = encoding.encode(&pong).expect("pong should encode") else {
+            panic!("msgpack messages should be binary frames");
+        };
+        let decoded: serde_json::Value =
+            rmp_serde::from_slice(&data).expect("frame should be msgpack");
+        assert_eq!(decoded["type"], "connection.pong");
+        assert_eq!(decoded["correlation_id"], "req-1");
+
+        let request = rmp_serde::to_vec_named(&ClientRequest::RoomJoin {
+            message_id: "req-2".to_string(),
+            room_code: "quiet-otter".to_string(),
+        })
+        .expect("request should encode");
+        let request = encoding
+            .decode(Message::Binary(request.into()))
+            .expect("binary request should decode");
+        assert_eq!(request.message_id(), "req-2");
+
+        let text = encoding
+            .decode(Message::Text(
+                r#"{"type":"connection.ping","message_id":"req-3"}"#.into(),
+            ))
+            .expect("text frames stay JSON");
+        assert_eq!(text.message_id(), "req-3");
+    }
+
+    #[test]
+    fn compact_boards_replace_cell_rows() {
+        let room_state = |snapshot| {
+            let state = SessionEvent::RoomState {
+                code: "quiet-otter".to_string(),
+                owner: None,
+                players: Vec::new(),
+                settings: RoomSettings::default(),
+                game: MatchView {
+                    state: MatchState::Waiting,
+                    game: Some(snapshot),
+                },
+            };
+            ServerMessage::from_session("m-1".to_string(), SessionMessage::Broadcast(state))
+        };
+        let mut snapshot = Game::new(GameDifficulty::TEST, 7)
+            .expect("test board should generate")
+            .snapshot();
+        let (width, height) = (usize::from(snapshot.width), usize::from(snapshot.height));
+
+        let mut state = room_state(snapshot.clone());
+        BoardEncoding::Cells.apply(&mut state);
+        let json = serde_json::to_value(&state).expect("state should serialize");
+        assert!(json.get("compact_board").is_none());
+        assert_eq!(
+            json["game"]["game"]["board"].as_array().map(Vec::len),
+            Some(height)
+        );
+
+        let mut state = room_state(snapshot.clone());
+        BoardEncoding::Compact.apply(&mut state);
+        let json = serde_json::to_value(&state).expect("state should serialize");
+        assert_eq!(json["compact_board"], "#".repeat(width * height));
+        assert!(json["game"]["game"].get("board").is_none());
+        assert_eq!(json["game"]["game"]["mines"], snapshot.mines);
+
+        // Rows are read top to bottom, each left to right.
+        snapshot.board = vec![
+            vec![
+                GameCell::HiddenCell,
+                GameCell::FlaggedCell,
+                GameCell::MinedCell,
+            ],
+            (0..=8).map(GameCell::VisibleCell).collect(),
+            vec![GameCell::VisibleCell(1), GameCell::FlaggedCell],
+        ];
+        let mut state = room_state(snapshot);
+        BoardEncoding::Compact.apply(&mut state);
+        let json = serde_json::to_value(&state).expect("state should serialize");
+        assert_eq!(json["compact_board"], "#F*0123456781F");
+    }
+
+    #[test]
+    fn hello_needs_only_a_protocol_version() {
+        let hello: ClientRequest = serde_json::from_str(
+            r#"{"type":"connection.hello","message_id":"req-1","protocol_version":1}"#,
+        )
+        .expect("minimal hello should parse");
+        let ClientRequest::ConnectionHello {
+            protocol_version,
+            client,
+            ..
+        } = hello
+        else {
+            panic!("expected connection.hello");
+        };
+        assert_eq!(protocol_version, 1);
+        assert!(client.is_none());
+
+        let features =
+            serde_json::to_value(ProtocolFeature::ALL).expect("features should serialize");
+        assert_eq!(features[0], "message_pack");
+    }
 }
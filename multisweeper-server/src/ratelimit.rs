@@ -0,0 +1,119 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    hash::Hash,
+    time::Duration,
+};
+
+use tokio::time::Instant;
+
+/// A token bucket that holds up to `capacity` tokens and regains one every `refill_every`.
+pub struct TokenBucket {
+    capacity: u32,
+    refill_every: Duration,
+    tokens: u32,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(capacity: u32, refill_every: Duration, now: Instant) -> Self {
+        TokenBucket {
+            capacity,
+            refill_every,
+            tokens: capacity,
+            last_refill: now,
+        }
+    }
+
+    /// Takes a token if one is available, returning whether it did.
+    pub fn try_take(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        if self.tokens == 0 {
+            return false;
+        }
+        self.tokens -= 1;
+        true
+    }
+
+    fn refill(&mut self, now: Instant) {
+        if self.tokens == self.capacity {
+            self.last_refill = now;
+            return;
+        }
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        let earned = (elapsed.as_nanos() / self.refill_every.as_nanos().max(1)) as u32;
+        if earned == 0 {
+            return;
+        }
+        self.tokens = self.tokens.saturating_add(earned).min(self.capacity);
+        self.last_refill += self.refill_every * earned;
+    }
+}
+
+/// Remembers the most recent `capacity` values, forgetting the oldest first.
+pub struct RecentSet<T> {
+    capacity: usize,
+    order: VecDeque<T>,
+    members: HashSet<T>,
+}
+
+impl<T: Clone + Eq + Hash> RecentSet<T> {
+    pub fn new(capacity: usize) -> Self {
+        RecentSet {
+            capacity,
+            order: VecDeque::with_capacity(capacity),
+            members: HashSet::with_capacity(capacity),
+        }
+    }
+
+    /// Records `value`, returning `false` if it is still remembered from an earlier insert.
+    pub fn insert(&mut self, value: T) -> bool {
+        if self.members.contains(&value) {
+            return false;
+        }
+        if self.order.len() == self.capacity
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.members.remove(&oldest);
+        }
+        self.order.push_back(value.clone());
+        self.members.insert(value);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::Instant;
+
+    use super::{RecentSet, TokenBucket};
+
+    #[test]
+    fn buckets_drain_and_refill_over_time() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(3, Duration::from_millis(100), now);
+
+        assert!((0..3).all(|_| bucket.try_take(now)));
+        assert!(!bucket.try_take(now));
+        assert!(!bucket.try_take(now + Duration::from_millis(99)));
+        assert!(bucket.try_take(now + Duration::from_millis(100)));
+        assert!(!bucket.try_take(now + Duration::from_millis(150)));
+
+        let later = now + Duration::from_secs(10);
+        assert!((0..3).all(|_| bucket.try_take(later)));
+        assert!(!bucket.try_take(later));
+    }
+
+    #[test]
+    fn recent_sets_forget_the_oldest_values() {
+        let mut seen = RecentSet::new(2);
+
+        assert!(seen.insert("a"));
+        assert!(!seen.insert("a"));
+        assert!(seen.insert("b"));
+        assert!(seen.insert("c"));
+        assert!(seen.insert("a"));
+        assert!(!seen.insert("c"));
+    }
+}
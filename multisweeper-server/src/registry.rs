@@ -1,13 +1,31 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, io};
 
 use anyhow::Result;
+use serde::Serialize;
 use thiserror::Error;
-use tokio::sync::{mpsc, oneshot};
+use tokio::{
+    sync::{mpsc, oneshot, watch},
+    task,
+    time::{Instant, sleep_until},
+};
 use tracing::{debug, info, warn};
 
 use crate::{
-    protocol::registry::RegistryMessage,
-    room::{Room, RoomAddr, RoomCode},
+    accounts::{Account, AccountError, AccountId, AccountStore, AccountToken, Identity},
+    matchmaking::{Matchmaker, QueueKey, QueuedPlayer},
+    persistence::RoomStore,
+    protocol::room::RoomControl,
+    protocol::{
+        registry::RegistryMessage,
+        session::{
+            MatchPhase, RoomChange, RoomSettings, RoomSummary, SessionEvent, SessionMessage,
+        },
+        wire::ClientDifficulty,
+    },
+    room::{Room, RoomAddr, RoomCode, RoomControlAddr},
+    session::{PlayerAddr, PlayerId},
+    shutdown::ShutdownSender,
+    stats::{MatchMode, MatchRecord, StatsError, StatsStore},
 };
 
 #[derive(Debug, Error)]
@@ -18,6 +36,10 @@ pub enum RegistryError {
     AddrDropped,
     #[error("reply failed")]
     ReplyFailed,
+    #[error("account error: {0}")]
+    Account(#[from] AccountError),
+    #[error("the server cannot host any more rooms")]
+    RoomLimitReached,
 }
 
 pub type RegistryMailbox = mpsc::Receiver<RegistryMessage>;
@@ -25,29 +47,190 @@ pub type RegistryAddr = mpsc::Sender<RegistryMessage>;
 
 type ReplyHandle<T> = oneshot::Sender<T>;
 
+#[derive(Debug, Clone, Copy)]
+pub struct RoomLimits {
+    /// Rooms that may exist at once; restored rooms count but are never refused.
+    pub max_rooms: usize,
+    /// Players and spectators a single room may hold.
+    pub max_players: usize,
+}
+
+impl Default for RoomLimits {
+    fn default() -> Self {
+        RoomLimits {
+            max_rooms: 1000,
+            max_players: 16,
+        }
+    }
+}
+
+/// A point-in-time summary of the server, as served on `/status`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerStatus {
+    pub rooms: usize,
+    pub sessions: usize,
+    pub queued_for_matchmaking: usize,
+    pub uptime_secs: u64,
+}
+
+/// A connected session as listed to operators.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSummary {
+    pub player_id: PlayerId,
+    pub account_id: Option<AccountId>,
+    pub display_name: Option<String>,
+    pub connected_secs: u64,
+}
+
+/// Rooms returned by `rooms.list` when the client does not ask for a page size.
+const DEFAULT_ROOM_PAGE: usize = 20;
+const MAX_ROOM_PAGE: usize = 100;
+
+/// Filters and paging for a room listing; private rooms are never listed.
+#[derive(Debug, Clone, Default)]
+pub struct RoomListQuery {
+    pub difficulty: Option<ClientDifficulty>,
+    pub mode: Option<MatchMode>,
+    pub match_state: Option<MatchPhase>,
+    pub open_only: bool,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+impl RoomListQuery {
+    fn matches(&self, room: &RoomSummary) -> bool {
+        !room.private
+            && self.difficulty.is_none_or(|d| d == room.difficulty)
+            && self.mode.is_none_or(|mode| mode == room.mode)
+            && self
+                .match_state
+                .is_none_or(|state| state == room.match_state)
+            && (!self.open_only || room.player_count < room.capacity)
+    }
+
+    /// Picks the requested page out of `rooms`, ordered by room code.
+    pub fn page<'a>(&self, rooms: impl IntoIterator<Item = &'a RoomSummary>) -> RoomPage {
+        let mut matching: Vec<_> = rooms
+            .into_iter()
+            .filter(|room| self.matches(room))
+            .collect();
+        matching.sort_by(|a, b| a.code.cmp(&b.code));
+        let limit = self.limit.unwrap_or(DEFAULT_ROOM_PAGE).min(MAX_ROOM_PAGE);
+        RoomPage {
+            total: matching.len(),
+            rooms: matching
+                .into_iter()
+                .skip(self.offset)
+                .take(limit)
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RoomPage {
+    pub rooms: Vec<RoomSummary>,
+    /// Rooms matching the query across all pages.
+    pub total: usize,
+}
+
+struct RoomHandle {
+    addr: RoomAddr,
+    control: RoomControlAddr,
+    summary: RoomSummary,
+    /// Revision of `summary`, so a late update cannot overwrite a newer one.
+    revision: u64,
+}
+
+struct SessionEntry {
+    /// Unset until the session attaches, shortly after its id is assigned.
+    addr: Option<PlayerAddr>,
+    account: Option<Account>,
+    connected_at: Instant,
+    /// Whether the session subscribed to room changes with `rooms.subscribe`.
+    browsing_rooms: bool,
+}
+
+/// Countdown given to rooms formed by matchmaking, whose players join already ready.
+const MATCHMADE_AUTO_START_SECS: u16 = 10;
+
 pub enum RegistryEvent {
     Mailbox(Option<RegistryMessage>),
+    MatchmakingDue,
 }
 
 pub struct Registry {
     entity_counter: u64,
-    rooms: HashMap<String, RoomAddr>,
+    rooms: HashMap<String, RoomHandle>,
+    /// Players whose sessions are still connected.
+    sessions: HashMap<PlayerId, SessionEntry>,
+    started_at: Instant,
+    accounts: AccountStore,
+    stats: StatsStore,
+    room_store: Option<RoomStore>,
+    room_limits: RoomLimits,
+    mailbox_capacity: usize,
+    matchmaker: Matchmaker,
+    /// Tells every room to stop; its receivers are dropped as the rooms finish.
+    shutdown: ShutdownSender,
     mailbox: RegistryMailbox,
     addr: RegistryAddr,
 }
 
 impl Registry {
     pub fn new() -> Self {
-        let (addr, mailbox) = mpsc::channel::<RegistryMessage>(10);
+        let mailbox_capacity = 10;
+        let (addr, mailbox) = mpsc::channel::<RegistryMessage>(mailbox_capacity);
 
         Registry {
             entity_counter: 0,
             rooms: HashMap::new(),
+            sessions: HashMap::new(),
+            started_at: Instant::now(),
+            accounts: AccountStore::in_memory(),
+            stats: StatsStore::in_memory(),
+            room_store: None,
+            room_limits: RoomLimits::default(),
+            mailbox_capacity,
+            matchmaker: Matchmaker::new(),
+            shutdown: watch::channel(None).0,
             mailbox,
             addr,
         }
     }
 
+    pub fn with_account_store(mut self, accounts: AccountStore) -> Self {
+        self.accounts = accounts;
+        self
+    }
+
+    pub fn with_stats_store(mut self, stats: StatsStore) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    pub fn with_room_store(mut self, room_store: RoomStore) -> Self {
+        self.room_store = Some(room_store);
+        self
+    }
+
+    pub fn with_room_limits(mut self, room_limits: RoomLimits) -> Self {
+        self.room_limits = room_limits;
+        self.matchmaker = Matchmaker::new().with_max_players(room_limits.max_players);
+        self
+    }
+
+    /// Sets the mailbox size of the registry and the rooms it creates. Call this before
+    /// handing out [`Registry::request_addr`], since it replaces the registry's mailbox.
+    pub fn with_mailbox_capacity(mut self, capacity: usize) -> Self {
+        let (addr, mailbox) = mpsc::channel(capacity);
+        self.addr = addr;
+        self.mailbox = mailbox;
+        self.mailbox_capacity = capacity;
+        self
+    }
+
     fn generate_name(&mut self, prefix: &str) -> String {
         let id = self.entity_counter;
         self.entity_counter += 1;
@@ -55,7 +238,95 @@ impl Registry {
     }
 
     fn register_player(&mut self) -> String {
-        self.generate_name("P")
+        let id = self.generate_name("P");
+        self.sessions.insert(
+            id.clone(),
+            SessionEntry {
+                addr: None,
+                account: None,
+                connected_at: Instant::now(),
+                browsing_rooms: false,
+            },
+        );
+        id
+    }
+
+    /// Forgets rooms whose tasks have finished.
+    fn prune_closed_rooms(&mut self) {
+        let closed: Vec<RoomCode> = self
+            .rooms
+            .iter()
+            .filter(|(_, handle)| handle.addr.is_closed())
+            .map(|(code, _)| code.clone())
+            .collect();
+        for code in closed {
+            self.remove_room(&code);
+        }
+    }
+
+    /// Forgets a room, telling browsing sessions if it was listed.
+    fn remove_room(&mut self, code: &RoomCode) -> Option<RoomHandle> {
+        let handle = self.rooms.remove(code)?;
+        if !handle.summary.private {
+            self.notify_browsers(RoomChange::Removed, code, None);
+        }
+        Some(handle)
+    }
+
+    /// Every public room, ordered by code.
+    fn public_rooms(&self) -> Vec<RoomSummary> {
+        let mut rooms: Vec<_> = self
+            .rooms
+            .values()
+            .filter(|handle| !handle.summary.private)
+            .map(|handle| handle.summary.clone())
+            .collect();
+        rooms.sort_by(|a, b| a.code.cmp(&b.code));
+        rooms
+    }
+
+    /// Pushes a room change to every session browsing rooms. A session whose mailbox is full
+    /// misses the update rather than holding up the registry; it can list rooms again.
+    fn notify_browsers(&self, change: RoomChange, code: &RoomCode, room: Option<RoomSummary>) {
+        for entry in self.sessions.values().filter(|entry| entry.browsing_rooms) {
+            if let Some(addr) = &entry.addr {
+                let _ = addr.try_send(SessionMessage::Broadcast(SessionEvent::RoomsUpdated {
+                    change,
+                    code: code.clone(),
+                    room: room.clone(),
+                }));
+            }
+        }
+    }
+
+    fn update_room(&mut self, summary: RoomSummary, revision: u64) {
+        let Some(handle) = self.rooms.get_mut(&summary.code) else {
+            return;
+        };
+        if revision <= handle.revision {
+            return;
+        }
+        let was_listed = !handle.summary.private;
+        handle.summary = summary.clone();
+        handle.revision = revision;
+        let change = match (was_listed, !summary.private) {
+            (true, true) => RoomChange::Changed,
+            (false, true) => RoomChange::Added,
+            (true, false) => RoomChange::Removed,
+            (false, false) => return,
+        };
+        let room = (change != RoomChange::Removed).then(|| summary.clone());
+        self.notify_browsers(change, &summary.code, room);
+    }
+
+    fn status(&mut self) -> ServerStatus {
+        self.prune_closed_rooms();
+        ServerStatus {
+            rooms: self.rooms.len(),
+            sessions: self.sessions.len(),
+            queued_for_matchmaking: self.matchmaker.queued(),
+            uptime_secs: self.started_at.elapsed().as_secs(),
+        }
     }
 
     fn generate_lobby_code(&self) -> RoomCode {
@@ -68,19 +339,105 @@ impl Registry {
         }
     }
 
-    async fn register_lobby(&mut self) -> (String, RoomAddr) {
-        let code = self.generate_lobby_code();
-        let room = Room::new(code.clone());
-        let room_handle = &room.request_handle();
+    /// Gives `room` the registry's limits, store and shutdown signal and starts its task. New
+    /// and restored rooms both come through here so they are configured alike.
+    fn spawn_room(&self, room: Room) -> RoomHandle {
+        let room = room
+            .with_mailbox_capacity(self.mailbox_capacity)
+            .with_max_players(self.room_limits.max_players)
+            .with_store(self.room_store.clone())
+            .with_shutdown(self.shutdown.subscribe());
+        let handle = RoomHandle {
+            addr: room.request_handle(),
+            control: room.control_handle(),
+            summary: room.summary(),
+            revision: 0,
+        };
         tokio::spawn(room.handle_connection());
-        self.rooms.insert(code.clone(), room_handle.clone());
+        handle
+    }
+
+    async fn register_lobby(
+        &mut self,
+        settings: RoomSettings,
+    ) -> Result<(String, RoomAddr), RegistryError> {
+        self.prune_closed_rooms();
+        if self.rooms.len() >= self.room_limits.max_rooms {
+            warn!(
+                target: "multisweeper.registry.room_limit_reached",
+                room_count = self.rooms.len(),
+                "room creation refused"
+            );
+            return Err(RegistryError::RoomLimitReached);
+        }
+        let code = self.generate_lobby_code();
+        let handle =
+            self.spawn_room(Room::new(code.clone(), self.addr.clone()).with_settings(settings));
+        let room_handle = handle.addr.clone();
+        if !handle.summary.private {
+            self.notify_browsers(RoomChange::Added, &code, Some(handle.summary.clone()));
+        }
+        self.rooms.insert(code.clone(), handle);
         info!(
             target: "multisweeper.registry.room_created",
             room_code = %code,
             room_count = self.rooms.len(),
             "room created"
         );
-        (code, room_handle.clone())
+        Ok((code, room_handle))
+    }
+
+    fn restore_rooms(&mut self) {
+        let Some(store) = self.room_store.clone() else {
+            return;
+        };
+        let snapshots = match store.load_all() {
+            Ok(snapshots) => snapshots,
+            Err(error) => {
+                warn!(
+                    target: "multisweeper.registry.restore_failed",
+                    error = %error,
+                    "room snapshots could not be read"
+                );
+                return;
+            }
+        };
+        for snapshot in snapshots {
+            let code = snapshot.code.clone();
+            match Room::restore(snapshot, self.addr.clone(), Some(store.clone())) {
+                Ok(room) if room.is_abandoned() => {
+                    if let Err(error) = store.remove(&code) {
+                        warn!(
+                            target: "multisweeper.registry.restore_failed",
+                            room_code = %code,
+                            error = %error,
+                            "abandoned room snapshot could not be removed"
+                        );
+                    }
+                    info!(
+                        target: "multisweeper.registry.room_discarded",
+                        room_code = %code,
+                        "room snapshot discarded because no seat can be reclaimed"
+                    );
+                }
+                Ok(room) => {
+                    let handle = self.spawn_room(room);
+                    self.rooms.insert(code.clone(), handle);
+                    info!(
+                        target: "multisweeper.registry.room_restored",
+                        room_code = %code,
+                        room_count = self.rooms.len(),
+                        "room restored"
+                    );
+                }
+                Err(error) => warn!(
+                    target: "multisweeper.registry.restore_failed",
+                    room_code = %code,
+                    error = %error,
+                    "room snapshot could not be replayed"
+                ),
+            }
+        }
     }
 
     pub fn request_addr(&self) -> RegistryAddr {
@@ -89,17 +446,19 @@ impl Registry {
 
     fn request_lobby(&mut self, code: RoomCode) -> Result<RoomAddr, RegistryError> {
         match self.rooms.get(&code) {
-            Some(handle) => Ok(handle.clone()),
+            Some(handle) => Ok(handle.addr.clone()),
             None => Err(RegistryError::RoomNotFound(code)),
         }
     }
 
-    fn request_lobbies(&mut self) -> Vec<&RoomCode> {
-        self.rooms.keys().collect()
+    fn request_lobbies(&mut self, query: &RoomListQuery) -> RoomPage {
+        self.prune_closed_rooms();
+        query.page(self.rooms.values().map(|handle| &handle.summary))
     }
 
     #[tracing::instrument(name = "registry.lifecycle", skip_all)]
     pub async fn handle_connections(mut self) -> Result<()> {
+        self.restore_rooms();
         match self.event_loop().await {
             Ok(()) => Ok(()),
             Err(e) => Err(e),
@@ -108,8 +467,12 @@ impl Registry {
 
     async fn event_loop(&mut self) -> Result<()> {
         loop {
+            let matchmaking_due = self.matchmaker.next_deadline();
             let event = tokio::select! {
-                msg = self.mailbox.recv() => RegistryEvent::Mailbox(msg)
+                msg = self.mailbox.recv() => RegistryEvent::Mailbox(msg),
+                _ = sleep_until(matchmaking_due.unwrap_or_else(Instant::now)), if matchmaking_due.is_some() => {
+                    RegistryEvent::MatchmakingDue
+                }
             };
 
             match event {
@@ -117,6 +480,16 @@ impl Registry {
                     let msg = self.receive_mailbox(msg)?;
                     self.handle_mailbox(msg).await?;
                 }
+                RegistryEvent::MatchmakingDue => self.form_matches().await,
+            }
+
+            // Keep serving until every room has wound down, since rooms report in as they stop.
+            if self.shutdown.borrow().is_some() && self.shutdown.is_closed() {
+                info!(
+                    target: "multisweeper.registry.shutdown",
+                    "registry stopped for server shutdown"
+                );
+                return Ok(());
             }
         }
     }
@@ -137,8 +510,11 @@ impl Registry {
         );
         match msg {
             RegistryMessage::CreateLobby(reply) => {
-                let (_code, addr) = self.register_lobby().await;
-                Self::handle_reply(reply, addr).await;
+                let result = self
+                    .register_lobby(RoomSettings::default())
+                    .await
+                    .map(|(_code, addr)| addr);
+                Self::handle_reply(reply, result).await;
                 Ok(())
             }
             RegistryMessage::RequestLobby { code, reply } => {
@@ -153,13 +529,37 @@ impl Registry {
                 Self::handle_reply(reply, result).await;
                 Ok(())
             }
-            RegistryMessage::QueryLobbies(reply) => {
-                let lobbies = self
-                    .request_lobbies()
-                    .iter()
-                    .map(ToString::to_string)
-                    .collect();
-                Self::handle_reply(reply, lobbies).await;
+            RegistryMessage::QueryLobbies { query, reply } => {
+                let page = self.request_lobbies(&query);
+                Self::handle_reply(reply, page).await;
+                Ok(())
+            }
+            RegistryMessage::RoomUpdated { summary, revision } => {
+                self.update_room(summary, revision);
+                Ok(())
+            }
+            RegistryMessage::RoomClosed(code) => {
+                // A newer room may have taken the code since; only forget a finished one.
+                if self
+                    .rooms
+                    .get(&code)
+                    .is_some_and(|handle| handle.addr.is_closed())
+                {
+                    self.remove_room(&code);
+                }
+                Ok(())
+            }
+            RegistryMessage::SubscribeRooms { id, reply } => {
+                if let Some(entry) = self.sessions.get_mut(&id) {
+                    entry.browsing_rooms = true;
+                }
+                Self::handle_reply(reply, self.public_rooms()).await;
+                Ok(())
+            }
+            RegistryMessage::UnsubscribeRooms(id) => {
+                if let Some(entry) = self.sessions.get_mut(&id) {
+                    entry.browsing_rooms = false;
+                }
                 Ok(())
             }
             RegistryMessage::CreatePlayer(reply) => {
@@ -167,9 +567,334 @@ impl Registry {
                 Self::handle_reply(reply, id).await;
                 Ok(())
             }
+            RegistryMessage::AttachSession { id, addr } => {
+                if let Some(entry) = self.sessions.get_mut(&id) {
+                    entry.addr = Some(addr);
+                }
+                Ok(())
+            }
+            RegistryMessage::ReleasePlayer(id) => {
+                self.sessions.remove(&id);
+                Ok(())
+            }
+            RegistryMessage::ListSessions(reply) => {
+                Self::handle_reply(reply, self.list_sessions()).await;
+                Ok(())
+            }
+            RegistryMessage::ListRooms(reply) => {
+                self.prune_closed_rooms();
+                let rooms = self
+                    .rooms
+                    .iter()
+                    .map(|(code, handle)| (code.clone(), handle.control.clone()))
+                    .collect();
+                Self::handle_reply(reply, rooms).await;
+                Ok(())
+            }
+            RegistryMessage::CloseRoom {
+                code,
+                reason,
+                reply,
+            } => {
+                let closed = self.close_room(&code, reason);
+                Self::handle_reply(reply, closed).await;
+                Ok(())
+            }
+            RegistryMessage::DisconnectSession { id, reason, reply } => {
+                let disconnected = self.disconnect_session(&id, reason);
+                Self::handle_reply(reply, disconnected).await;
+                Ok(())
+            }
+            RegistryMessage::Announce { text, reply } => {
+                let delivered = self.announce(text);
+                Self::handle_reply(reply, delivered).await;
+                Ok(())
+            }
+            RegistryMessage::QueryStatus(reply) => {
+                let status = self.status();
+                Self::handle_reply(reply, status).await;
+                Ok(())
+            }
+            RegistryMessage::IdentifyPlayer {
+                id,
+                token,
+                display_name,
+                reply,
+            } => {
+                let result = self.identify_player(&id, token, display_name).await;
+                if let (Ok(identity), Some(entry)) = (&result, self.sessions.get_mut(&id)) {
+                    entry.account = Some(identity.account.clone());
+                }
+                Self::handle_reply(reply, result).await;
+                Ok(())
+            }
+            RegistryMessage::RecordMatch(record) => {
+                let room_code = record.room_code.clone();
+                if let Err(error) = self.record_match(record).await {
+                    warn!(
+                        target: "multisweeper.registry.stats_store_failed",
+                        room_code = %room_code,
+                        error = %error,
+                        "match record could not be saved"
+                    );
+                }
+                info!(
+                    target: "multisweeper.registry.match_recorded",
+                    room_code = %room_code,
+                    match_count = self.stats.len(),
+                    "match recorded"
+                );
+                Ok(())
+            }
+            RegistryMessage::QueryStats { account_id, reply } => {
+                Self::handle_reply(reply, self.stats.player_stats(&account_id)).await;
+                Ok(())
+            }
+            RegistryMessage::QueryLeaderboard {
+                difficulty,
+                limit,
+                reply,
+            } => {
+                Self::handle_reply(reply, self.stats.leaderboard(difficulty, limit)).await;
+                Ok(())
+            }
+            RegistryMessage::JoinMatchmaking { key, player, reply } => {
+                let id = player.id.clone();
+                let queued = self.matchmaker.join(key, player, Instant::now());
+                debug!(
+                    target: "multisweeper.registry.matchmaking_queued",
+                    player_id = %id,
+                    mode = ?key.0,
+                    difficulty = ?key.1,
+                    queued,
+                    "player queued for matchmaking"
+                );
+                Self::handle_reply(reply, queued).await;
+                self.form_matches().await;
+                Ok(())
+            }
+            RegistryMessage::LeaveMatchmaking { id, reply } => {
+                let removed = self.matchmaker.leave(&id);
+                Self::handle_reply(reply, removed).await;
+                Ok(())
+            }
+            RegistryMessage::Shutdown { notice, reply } => {
+                info!(
+                    target: "multisweeper.registry.shutdown_started",
+                    room_count = self.rooms.len(),
+                    reason = %notice.reason,
+                    "stopping rooms for server shutdown"
+                );
+                self.shutdown.send_replace(Some(notice));
+                // Rooms keep sending updates while they wind down, so wait for them elsewhere
+                // and leave the mailbox free.
+                let shutdown = self.shutdown.clone();
+                tokio::spawn(async move {
+                    shutdown.closed().await;
+                    Self::handle_reply(reply, ()).await;
+                });
+                Ok(())
+            }
         }
     }
 
+    /// Creates a room for every matchmaking group that is ready and sends its players there.
+    async fn form_matches(&mut self) {
+        for (key, players) in self.matchmaker.take_ready(Instant::now()) {
+            self.create_matched_room(key, players).await;
+        }
+    }
+
+    async fn create_matched_room(
+        &mut self,
+        (mode, difficulty): QueueKey,
+        players: Vec<QueuedPlayer>,
+    ) {
+        let settings = RoomSettings {
+            auto_start_secs: MATCHMADE_AUTO_START_SECS,
+            auto_start_difficulty: difficulty,
+            ..RoomSettings::default()
+        };
+        let code = match self.register_lobby(settings).await {
+            Ok((code, _addr)) => code,
+            Err(_) => {
+                // Keep the group waiting; it is retried at the queue's next deadline.
+                let now = Instant::now();
+                for player in players {
+                    self.matchmaker.join((mode, difficulty), player, now);
+                }
+                return;
+            }
+        };
+        info!(
+            target: "multisweeper.registry.match_formed",
+            room_code = %code,
+            mode = ?mode,
+            difficulty = ?difficulty,
+            player_count = players.len(),
+            "matchmaking formed a room"
+        );
+        for player in players {
+            let _ = player
+                .addr
+                .send(SessionMessage::Reply {
+                    request_id: player.request_id,
+                    message: SessionEvent::MatchFound {
+                        room_code: code.clone(),
+                    },
+                })
+                .await;
+        }
+    }
+
+    fn list_sessions(&self) -> Vec<SessionSummary> {
+        let mut sessions: Vec<_> = self
+            .sessions
+            .iter()
+            .map(|(id, entry)| SessionSummary {
+                player_id: id.clone(),
+                account_id: entry.account.as_ref().map(|account| account.id.clone()),
+                display_name: entry
+                    .account
+                    .as_ref()
+                    .map(|account| account.display_name.clone()),
+                connected_secs: entry.connected_at.elapsed().as_secs(),
+            })
+            .collect();
+        sessions.sort_by(|a, b| a.player_id.cmp(&b.player_id));
+        sessions
+    }
+
+    fn close_room(&mut self, code: &RoomCode, reason: String) -> bool {
+        let Some(handle) = self.remove_room(code) else {
+            return false;
+        };
+        info!(
+            target: "multisweeper.registry.room_closed",
+            room_code = %code,
+            reason = %reason,
+            "closing room on operator request"
+        );
+        // Rooms message the registry themselves, so the registry never waits on one.
+        let control = handle.control;
+        tokio::spawn(async move {
+            let _ = control.send(RoomControl::Close { reason }).await;
+        });
+        true
+    }
+
+    fn disconnect_session(&mut self, id: &PlayerId, reason: String) -> bool {
+        let Some(addr) = self.sessions.get(id).and_then(|entry| entry.addr.clone()) else {
+            return false;
+        };
+        info!(
+            target: "multisweeper.registry.session_disconnected",
+            player_id = %id,
+            reason = %reason,
+            "disconnecting session on operator request"
+        );
+        tokio::spawn(async move {
+            let _ = addr
+                .send(SessionMessage::Broadcast(SessionEvent::Disconnected {
+                    reason,
+                }))
+                .await;
+        });
+        true
+    }
+
+    fn announce(&self, text: String) -> usize {
+        let recipients: Vec<PlayerAddr> = self
+            .sessions
+            .values()
+            .filter_map(|entry| entry.addr.clone())
+            .collect();
+        let delivered = recipients.len();
+        info!(
+            target: "multisweeper.registry.announcement",
+            session_count = delivered,
+            "broadcasting an announcement"
+        );
+        tokio::spawn(async move {
+            for addr in recipients {
+                let _ = addr
+                    .send(SessionMessage::Broadcast(SessionEvent::Announcement {
+                        text: text.clone(),
+                    }))
+                    .await;
+            }
+        });
+        delivered
+    }
+
+    async fn identify_player(
+        &mut self,
+        id: &PlayerId,
+        token: Option<AccountToken>,
+        display_name: Option<String>,
+    ) -> Result<Identity, RegistryError> {
+        let is_new = token.is_none();
+        let identity = match self.accounts.identify(token, display_name) {
+            Ok(identity) => identity,
+            Err(error) => {
+                warn!(
+                    target: "multisweeper.registry.identify_failed",
+                    player_id = %id,
+                    error = %error,
+                    "player identification failed"
+                );
+                return Err(error.into());
+            }
+        };
+        if let Err(error) = self.save_accounts().await {
+            warn!(
+                target: "multisweeper.registry.account_store_failed",
+                error = %error,
+                account_created = is_new,
+                "account store could not be saved"
+            );
+            // A token that would not survive a restart is worse than no token at all.
+            if is_new {
+                self.accounts.forget(&identity.token);
+                return Err(error.into());
+            }
+        }
+        info!(
+            target: "multisweeper.registry.player_identified",
+            player_id = %id,
+            account_id = %identity.account.id,
+            account_created = is_new,
+            account_count = self.accounts.len(),
+            "player identified"
+        );
+        Ok(identity)
+    }
+
+    /// Writes any account changes on the blocking pool, keeping file io off the registry's
+    /// worker thread.
+    async fn save_accounts(&mut self) -> Result<(), AccountError> {
+        let Some(pending) = self.accounts.pending_save()? else {
+            return Ok(());
+        };
+        task::spawn_blocking(move || pending.write())
+            .await
+            .unwrap_or_else(|error| Err(io::Error::other(error).into()))?;
+        self.accounts.mark_saved();
+        Ok(())
+    }
+
+    /// Keeps a finished match and appends it to the stats file on the blocking pool.
+    async fn record_match(&mut self, record: MatchRecord) -> Result<(), StatsError> {
+        let Some(pending) = self.stats.push(record)? else {
+            return Ok(());
+        };
+        task::spawn_blocking(move || pending.write())
+            .await
+            .unwrap_or_else(|error| Err(io::Error::other(error).into()))?;
+        self.stats.mark_appended();
+        Ok(())
+    }
+
     async fn handle_reply<T>(reply: ReplyHandle<T>, msg: T) -> () {
         let _ = reply.send(msg);
     }
@@ -179,8 +904,27 @@ fn registry_message_name(message: &RegistryMessage) -> &'static str {
     match message {
         RegistryMessage::CreateLobby(_) => "create_lobby",
         RegistryMessage::RequestLobby { .. } => "request_lobby",
-        RegistryMessage::QueryLobbies(_) => "query_lobbies",
+        RegistryMessage::QueryLobbies { .. } => "query_lobbies",
+        RegistryMessage::RoomUpdated { .. } => "room_updated",
+        RegistryMessage::RoomClosed(_) => "room_closed",
+        RegistryMessage::SubscribeRooms { .. } => "subscribe_rooms",
+        RegistryMessage::UnsubscribeRooms(_) => "unsubscribe_rooms",
         RegistryMessage::CreatePlayer(_) => "create_player",
+        RegistryMessage::AttachSession { .. } => "attach_session",
+        RegistryMessage::ReleasePlayer(_) => "release_player",
+        RegistryMessage::QueryStatus(_) => "query_status",
+        RegistryMessage::ListSessions(_) => "list_sessions",
+        RegistryMessage::ListRooms(_) => "list_rooms",
+        RegistryMessage::CloseRoom { .. } => "close_room",
+        RegistryMessage::DisconnectSession { .. } => "disconnect_session",
+        RegistryMessage::Announce { .. } => "announce",
+        RegistryMessage::IdentifyPlayer { .. } => "identify_player",
+        RegistryMessage::RecordMatch(_) => "record_match",
+        RegistryMessage::QueryStats { .. } => "query_stats",
+        RegistryMessage::QueryLeaderboard { .. } => "query_leaderboard",
+        RegistryMessage::JoinMatchmaking { .. } => "join_matchmaking",
+        RegistryMessage::LeaveMatchmaking { .. } => "leave_matchmaking",
+        RegistryMessage::Shutdown { .. } => "shutdown",
     }
 }
 
@@ -189,3 +933,300 @@ impl Default for Registry {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::{mpsc, oneshot};
+
+    use crate::{
+        accounts::{Account, AccountStore},
+        persistence::{MatchSnapshot, RoomSnapshot, RoomStore, SeatSnapshot},
+        protocol::{
+            registry::RegistryMessage,
+            room::RoomControl,
+            session::{
+                MatchPhase, PlayerState, RoomChange, RoomSettings, RoomSummary, SessionEvent,
+                SessionMessage,
+            },
+            wire::ClientDifficulty,
+        },
+        session::PlayerId,
+        shutdown::ShutdownNotice,
+        stats::MatchMode,
+    };
+
+    use super::{Registry, RegistryError, RoomHandle, RoomListQuery};
+
+    fn summary(code: &str, player_count: usize, match_state: MatchPhase) -> RoomSummary {
+        RoomSummary {
+            code: code.to_string(),
+            player_count,
+            capacity: 4,
+            owner_name: None,
+            mode: MatchMode::TurnBased,
+            difficulty: ClientDifficulty::Easy,
+            match_state,
+            private: false,
+        }
+    }
+
+    #[test]
+    fn room_listing_filters_then_pages_by_code() {
+        let hidden = RoomSummary {
+            private: true,
+            ..summary("a-private", 1, MatchPhase::Waiting)
+        };
+        let rooms = [
+            summary("d-full", 4, MatchPhase::Waiting),
+            summary("b-open", 1, MatchPhase::Waiting),
+            summary("c-busy", 2, MatchPhase::Playing),
+            summary("e-open", 3, MatchPhase::Waiting),
+            hidden,
+        ];
+
+        let query = RoomListQuery {
+            match_state: Some(MatchPhase::Waiting),
+            open_only: true,
+            ..RoomListQuery::default()
+        };
+        let page = query.page(&rooms);
+        assert_eq!(page.total, 2);
+        let codes: Vec<_> = page.rooms.iter().map(|room| room.code.as_str()).collect();
+        assert_eq!(codes, ["b-open", "e-open"]);
+
+        let second_page = RoomListQuery {
+            offset: 1,
+            limit: Some(2),
+            ..RoomListQuery::default()
+        }
+        .page(&rooms);
+        assert_eq!(second_page.total, 4);
+        let codes: Vec<_> = second_page
+            .rooms
+            .iter()
+            .map(|room| room.code.as_str())
+            .collect();
+        assert_eq!(codes, ["c-busy", "d-full"]);
+    }
+
+    /// Registers a room under `summary.code`, returning the mailbox its control messages land in.
+    fn add_room(registry: &mut Registry, summary: RoomSummary) -> mpsc::Receiver<RoomControl> {
+        let (addr, _) = mpsc::channel(10);
+        let (control, control_mailbox) = mpsc::channel(10);
+        let handle = RoomHandle {
+            addr,
+            control,
+            summary: summary.clone(),
+            revision: 0,
+        };
+        registry.rooms.insert(summary.code, handle);
+        control_mailbox
+    }
+
+    /// Registers an attached session, returning its id and the mailbox its messages land in.
+    fn add_session(
+        registry: &mut Registry,
+        browsing_rooms: bool,
+    ) -> (PlayerId, mpsc::Receiver<SessionMessage>) {
+        let id = registry.register_player();
+        let (addr, mailbox) = mpsc::channel(10);
+        let entry = registry
+            .sessions
+            .get_mut(&id)
+            .expect("player was just created");
+        entry.addr = Some(addr);
+        entry.browsing_rooms = browsing_rooms;
+        (id, mailbox)
+    }
+
+    #[tokio::test]
+    async fn restored_rooms_get_the_configured_limits() {
+        let dir =
+            std::env::temp_dir().join(format!("multisweeper-rooms-{:016x}", rand::random::<u64>()));
+        let store = RoomStore::new(&dir);
+        let snapshot = RoomSnapshot {
+            code: "quiet-otter".to_string(),
+            owner: Some("ada".to_string()),
+            seats: vec![SeatSnapshot {
+                id: "P00001".to_string(),
+                account: Some(Account {
+                    id: "ada".to_string(),
+                    display_name: "Ada".to_string(),
+                }),
+                state: PlayerState::Spectator,
+            }],
+            settings: RoomSettings::default(),
+            banned: Default::default(),
+            match_state: MatchSnapshot::Waiting,
+        };
+        let contents = serde_json::to_vec(&snapshot).expect("snapshot should serialize");
+        store
+            .save(&snapshot.code, &contents)
+            .expect("snapshot should save");
+        let mut registry = Registry::new()
+            .with_mailbox_capacity(3)
+            .with_room_store(store);
+
+        registry.restore_rooms();
+        let room = &registry.rooms["quiet-otter"];
+        assert_eq!(room.addr.max_capacity(), 3);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn closing_a_room_removes_it_and_tells_it_why() {
+        let mut registry = Registry::new();
+        let mut control = add_room(
+            &mut registry,
+            summary("quiet-otter", 2, MatchPhase::Waiting),
+        );
+
+        assert!(!registry.close_room(&"loud-heron".to_string(), "gone".to_string()));
+        assert!(registry.close_room(&"quiet-otter".to_string(), "gone".to_string()));
+        assert!(registry.rooms.is_empty());
+        assert!(matches!(
+            control.recv().await,
+            Some(RoomControl::Close { reason }) if reason == "gone"
+        ));
+    }
+
+    #[tokio::test]
+    async fn disconnecting_a_session_sends_it_the_reason() {
+        let mut registry = Registry::new();
+        let (id, mut mailbox) = add_session(&mut registry, false);
+
+        assert!(!registry.disconnect_session(&"P99999".to_string(), "bye".to_string()));
+        assert!(registry.disconnect_session(&id, "bye".to_string()));
+        assert!(matches!(
+            mailbox.recv().await,
+            Some(SessionMessage::Broadcast(SessionEvent::Disconnected { reason })) if reason == "bye"
+        ));
+    }
+
+    #[tokio::test]
+    async fn announcements_reach_every_attached_session() {
+        let mut registry = Registry::new();
+        let mut mailboxes = [
+            add_session(&mut registry, false).1,
+            add_session(&mut registry, true).1,
+        ];
+        registry.register_player();
+
+        assert_eq!(registry.announce("restarting soon".to_string()), 2);
+        for mailbox in &mut mailboxes {
+            assert!(matches!(
+                mailbox.recv().await,
+                Some(SessionMessage::Broadcast(SessionEvent::Announcement { text }))
+                    if text == "restarting soon"
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn browsing_sessions_hear_about_public_rooms_only() {
+        let mut registry = Registry::new();
+        let (_, mut browser) = add_session(&mut registry, true);
+        let (_, mut bystander) = add_session(&mut registry, false);
+        let public = summary("quiet-otter", 1, MatchPhase::Waiting);
+        let private = RoomSummary {
+            private: true,
+            ..summary("loud-heron", 1, MatchPhase::Waiting)
+        };
+        let _public_control = add_room(&mut registry, public.clone());
+        let _private_control = add_room(&mut registry, private.clone());
+
+        registry.update_room(
+            RoomSummary {
+                player_count: 2,
+                ..public.clone()
+            },
+            1,
+        );
+        registry.update_room(
+            RoomSummary {
+                player_count: 2,
+                ..private.clone()
+            },
+            1,
+        );
+        registry.update_room(
+            RoomSummary {
+                private: false,
+                ..private.clone()
+            },
+            2,
+        );
+        registry.update_room(public.clone(), 1);
+        registry.remove_room(&public.code);
+        registry.update_room(private.clone(), 3);
+        registry.remove_room(&private.code);
+
+        let mut changes = Vec::new();
+        while let Ok(message) = browser.try_recv() {
+            let SessionMessage::Broadcast(SessionEvent::RoomsUpdated { change, code, room }) =
+                message
+            else {
+                panic!("browsers only get room updates");
+            };
+            changes.push((change, code, room.map(|room| room.player_count)));
+        }
+        assert_eq!(
+            changes,
+            [
+                (RoomChange::Changed, public.code.clone(), Some(2)),
+                (RoomChange::Added, private.code.clone(), Some(1)),
+                (RoomChange::Removed, public.code.clone(), None),
+                (RoomChange::Removed, private.code.clone(), None),
+            ]
+        );
+        assert!(bystander.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn new_accounts_that_cannot_be_saved_are_refused() {
+        let dir = std::env::temp_dir().join(format!(
+            "multisweeper-accounts-{:016x}",
+            rand::random::<u64>()
+        ));
+        let store = AccountStore::open(dir.join("accounts.json")).expect("missing file is empty");
+        // A file where the store's directory should be makes every save fail.
+        std::fs::write(&dir, "").expect("temp file should be writable");
+        let mut registry = Registry::new().with_account_store(store);
+
+        let result = registry
+            .identify_player(&"P00000".to_string(), None, Some("Ada".to_string()))
+            .await;
+        assert!(matches!(result, Err(RegistryError::Account(_))));
+        assert_eq!(registry.accounts.len(), 0);
+        let _ = std::fs::remove_file(dir);
+    }
+
+    #[tokio::test]
+    async fn registry_keeps_answering_while_rooms_shut_down() {
+        let registry = Registry::new();
+        let room = registry.shutdown.subscribe();
+        let addr = registry.request_addr();
+        tokio::spawn(registry.handle_connections());
+
+        let (reply, mut stopped) = oneshot::channel();
+        let notice = ShutdownNotice {
+            reason: "restarting".to_string(),
+            restart_in_secs: None,
+        };
+        addr.send(RegistryMessage::Shutdown { notice, reply })
+            .await
+            .expect("registry is running");
+        let (reply, page) = oneshot::channel();
+        addr.send(RegistryMessage::QueryLobbies {
+            query: RoomListQuery::default(),
+            reply,
+        })
+        .await
+        .expect("registry is running");
+        page.await.expect("registry answers during shutdown");
+        assert!(stopped.try_recv().is_err());
+
+        drop(room);
+        stopped.await.expect("shutdown completes once rooms stop");
+    }
+}
@@ -1,26 +1,53 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use anyhow::Result;
-use multisweeper_core::{Game, GameActionResult, GameDifficulty, GameError, GameSnapshot};
-use rand::random;
+use multisweeper_core::{
+    Game, GameAction, GameActionResult, GameCell, GameDifficulty, GameError, GameSnapshot,
+    SystemClock,
+};
+use rand::{SeedableRng, random, rngs::StdRng, seq::SliceRandom};
+use serde::Serialize;
 use thiserror::Error;
-use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::{
+    sync::mpsc::{self, Receiver, Sender},
+    task,
+    time::{Instant, sleep_until},
+};
 use tracing::{debug, error, info, warn};
 
 use crate::{
+    accounts::{Account, AccountId},
+    chat::{ChatError, ChatLog},
+    metrics::METRICS,
+    persistence::{GameRecord, MatchSnapshot, RoomSnapshot, RoomStore, SeatSnapshot},
     protocol::{
-        room::{PlayerCommand, RequestContext, RoomMessage},
+        registry::RegistryMessage,
+        room::{PlayerCommand, RequestContext, RoomControl, RoomMessage, RoomSettingsUpdate},
         session::{
-            ClientError, ErrorCode, MatchState as ProtocolMatchState, MatchView, PlayerState,
-            PlayerView, SessionEvent, SessionMessage,
+            ChatMessageView, ClientError, ErrorCode, MatchPhase, MatchState as ProtocolMatchState,
+            MatchView, PlayerState, PlayerView, RoomSettings, RoomSummary, SessionEvent,
+            SessionMessage, TurnOrder, TurnPolicy,
         },
     },
+    registry::RegistryAddr,
     session::{PlayerAddr, PlayerId},
+    shutdown::{self, ShutdownSignal},
+    stats::{MatchMode, MatchOutcome, MatchParticipant, MatchRecord},
 };
 
 pub type RoomCode = String;
 pub type RoomMailbox = Receiver<RoomMessage>;
 pub type RoomAddr = Sender<RoomMessage>;
+pub type RoomControlAddr = Sender<RoomControl>;
+
+/// How long seats restored from a snapshot are held for their accounts to reclaim.
+const RESERVATION_WINDOW: Duration = Duration::from_secs(300);
+const MAX_SPECTATOR_DELAY_SECS: u16 = 300;
+const MAX_AUTO_START_SECS: u16 = 120;
 
 #[derive(Debug, Clone, Error)]
 pub enum RoomError {
@@ -30,7 +57,7 @@ pub enum RoomError {
     PlayerDropped(PlayerId),
     #[error("no such player {0}")]
     NoPlayerFound(PlayerId),
-    #[error("only the room owner can start the game")]
+    #[error("only the room owner can do that")]
     NotOwner,
     #[error("game has already started")]
     GameAlreadyStarted,
@@ -48,6 +75,18 @@ pub enum RoomError {
     AllPlayersDropped,
     #[error("game error: {0}")]
     Game(#[from] GameError),
+    #[error("{0}")]
+    Chat(#[from] ChatError),
+    #[error("invalid room settings: {0}")]
+    InvalidSettings(String),
+    #[error("{ready} of the {required} required players are ready")]
+    NotEnoughReady { ready: usize, required: usize },
+    #[error("player {0} is banned from this room")]
+    Banned(PlayerId),
+    #[error("the room owner cannot target themselves")]
+    CannotTargetSelf,
+    #[error("room is full")]
+    RoomFull,
 }
 
 impl RoomError {
@@ -64,6 +103,13 @@ impl RoomError {
             Self::PlayerNotCurrent(_) => ErrorCode::NotCurrentPlayer,
             Self::AllPlayersDropped => ErrorCode::NoPlayersRemaining,
             Self::Game(_) => ErrorCode::GameError,
+            Self::Chat(ChatError::RateLimited) => ErrorCode::RateLimited,
+            Self::Chat(_) => ErrorCode::InvalidChatMessage,
+            Self::InvalidSettings(_) => ErrorCode::InvalidSettings,
+            Self::NotEnoughReady { .. } => ErrorCode::PlayersNotReady,
+            Self::Banned(_) => ErrorCode::BannedFromRoom,
+            Self::CannotTargetSelf => ErrorCode::CannotTargetSelf,
+            Self::RoomFull => ErrorCode::RoomFull,
         };
         ClientError::new(code, self.to_string())
     }
@@ -71,19 +117,34 @@ impl RoomError {
 
 struct PlayerRecord {
     address: PlayerAddr,
+    account: Option<Account>,
+    state: PlayerState,
+    /// Order the player took their seat in; the longest-seated player inherits ownership.
+    seat: u64,
+    ready: bool,
+}
+
+struct ReservedSeat {
+    account: Account,
     state: PlayerState,
 }
 
 struct PlayingMatch {
     game: Game,
     participants: Vec<PlayerId>,
+    accounts: HashMap<PlayerId, Account>,
+    scores: HashMap<PlayerId, u32>,
     last_player: Option<PlayerId>,
     current_player: PlayerId,
+    /// Players who hit a mine, in the order they were eliminated.
+    eliminated: Vec<PlayerId>,
+    /// Reveals the current player has made this turn.
+    reveals_this_turn: u8,
 }
 
 enum RoomMatchState {
     Waiting,
-    Playing(PlayingMatch),
+    Playing(Box<PlayingMatch>),
     Won { final_snapshot: GameSnapshot },
     NoWinner { final_snapshot: GameSnapshot },
 }
@@ -92,36 +153,410 @@ pub struct Room {
     code: RoomCode,
     mailbox: RoomMailbox,
     addr: RoomAddr,
+    control_mailbox: Receiver<RoomControl>,
+    control_addr: RoomControlAddr,
+    registry: RegistryAddr,
     players: HashMap<PlayerId, PlayerRecord>,
     owner: Option<PlayerId>,
     match_state: RoomMatchState,
+    store: Option<RoomStore>,
+    persisted: Vec<u8>,
+    /// Seats restored from a snapshot, keyed by the account that may reclaim them.
+    reserved: HashMap<AccountId, ReservedSeat>,
+    reservation_deadline: Option<Instant>,
+    chat: ChatLog,
+    settings: RoomSettings,
+    /// Match views waiting out the spectator delay, oldest first.
+    spectator_feed: VecDeque<(Instant, MatchView)>,
+    /// The most recent match view released to spectators while the feed is delayed.
+    spectator_view: MatchView,
+    /// Player and account ids kept from joining.
+    banned: HashSet<String>,
+    next_seat: u64,
+    /// When the auto-start countdown ends, if one is running.
+    countdown_deadline: Option<Instant>,
+    /// Players eliminated in the previous match, earliest first.
+    last_losers: Vec<PlayerId>,
+    shutdown: Option<ShutdownSignal>,
+    /// Players and spectators the room may hold, reserved seats included.
+    max_players: usize,
+    /// The summary last sent to the registry, and how many have been sent.
+    published_summary: Option<RoomSummary>,
+    summary_revision: u64,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct RoomState {
     pub code: RoomCode,
     pub players: Vec<PlayerView>,
     pub owner: Option<PlayerId>,
+    pub settings: RoomSettings,
     pub match_state: MatchView,
 }
 
 enum RoomEvent {
     Session(Option<RoomMessage>),
+    Control(RoomControl),
+    ReservationExpired,
+    SpectatorFeedDue,
+    CountdownElapsed,
+    Shutdown,
 }
 
 impl Room {
-    pub fn new(code: RoomCode) -> Self {
+    pub fn new(code: RoomCode, registry: RegistryAddr) -> Self {
         let (sender, receiver) = mpsc::channel(10);
+        let (control_addr, control_mailbox) = mpsc::channel(1);
         Room {
             code,
             mailbox: receiver,
             addr: sender,
+            control_mailbox,
+            control_addr,
+            registry,
             players: HashMap::new(),
             owner: None,
             match_state: RoomMatchState::Waiting,
+            store: None,
+            persisted: Vec::new(),
+            reserved: HashMap::new(),
+            reservation_deadline: None,
+            chat: ChatLog::new(),
+            settings: RoomSettings::default(),
+            spectator_feed: VecDeque::new(),
+            spectator_view: MatchView {
+                state: ProtocolMatchState::Waiting,
+                game: None,
+            },
+            banned: HashSet::new(),
+            next_seat: 0,
+            countdown_deadline: None,
+            last_losers: Vec::new(),
+            shutdown: None,
+            max_players: usize::MAX,
+            published_summary: None,
+            summary_revision: 0,
+        }
+    }
+
+    pub fn with_store(mut self, store: Option<RoomStore>) -> Self {
+        self.store = store;
+        self
+    }
+
+    pub fn with_settings(mut self, settings: RoomSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Replaces the room's mailbox; call this before taking a [`Room::request_handle`].
+    pub fn with_mailbox_capacity(mut self, capacity: usize) -> Self {
+        let (sender, receiver) = mpsc::channel(capacity);
+        self.addr = sender;
+        self.mailbox = receiver;
+        self
+    }
+
+    pub fn with_max_players(mut self, max_players: usize) -> Self {
+        self.max_players = max_players;
+        self
+    }
+
+    pub fn with_shutdown(mut self, shutdown: ShutdownSignal) -> Self {
+        self.shutdown = Some(shutdown);
+        self
+    }
+
+    /// Rebuilds a room from its snapshot. Seats belonging to accounts are held for
+    /// [`RESERVATION_WINDOW`] under the account id; anonymous seats cannot be reclaimed, so a
+    /// room with only those comes back [abandoned](Room::is_abandoned).
+    pub fn restore(
+        snapshot: RoomSnapshot,
+        registry: RegistryAddr,
+        store: Option<RoomStore>,
+    ) -> Result<Self, RoomError> {
+        let mut room = Room::new(snapshot.code, registry).with_store(store);
+        room.owner = snapshot.owner;
+        room.settings = snapshot.settings;
+        room.banned = snapshot.banned;
+        room.match_state = match snapshot.match_state {
+            MatchSnapshot::Waiting => RoomMatchState::Waiting,
+            MatchSnapshot::Playing(record) => {
+                let game = Game::replay(
+                    record.difficulty.into(),
+                    record.seed,
+                    &record.moves,
+                    SystemClock,
+                )?;
+                RoomMatchState::Playing(Box::new(PlayingMatch {
+                    game,
+                    participants: record.participants,
+                    accounts: record.accounts,
+                    scores: record.scores,
+                    last_player: record.last_player,
+                    current_player: record.current_player,
+                    eliminated: record.eliminated,
+                    reveals_this_turn: record.reveals_this_turn,
+                }))
+            }
+            MatchSnapshot::Won { final_snapshot } => RoomMatchState::Won { final_snapshot },
+            MatchSnapshot::NoWinner { final_snapshot } => {
+                RoomMatchState::NoWinner { final_snapshot }
+            }
+        };
+        for seat in snapshot.seats {
+            let Some(account) = seat.account else {
+                continue;
+            };
+            room.rename_player(&seat.id, &account.id);
+            room.reserved.insert(
+                account.id.clone(),
+                ReservedSeat {
+                    account,
+                    state: seat.state,
+                },
+            );
+        }
+        if room
+            .owner
+            .as_ref()
+            .is_some_and(|owner| !room.reserved.contains_key(owner))
+        {
+            room.owner = None;
+        }
+        if !room.reserved.is_empty() {
+            room.reservation_deadline = Some(Instant::now() + RESERVATION_WINDOW);
+        }
+        info!(
+            target: "multisweeper.room.restored",
+            room_code = %room.code,
+            reserved_seats = room.reserved.len(),
+            "room restored from snapshot"
+        );
+        Ok(room)
+    }
+
+    fn snapshot(&self) -> RoomSnapshot {
+        let seats = self
+            .players
+            .iter()
+            .map(|(id, player)| SeatSnapshot {
+                id: id.clone(),
+                account: player.account.clone(),
+                state: player.state.clone(),
+            })
+            .chain(self.reserved.iter().map(|(account_id, seat)| SeatSnapshot {
+                id: account_id.clone(),
+                account: Some(seat.account.clone()),
+                state: seat.state.clone(),
+            }))
+            .collect();
+        let match_state = match &self.match_state {
+            RoomMatchState::Waiting => MatchSnapshot::Waiting,
+            RoomMatchState::Playing(active_match) => MatchSnapshot::Playing(GameRecord {
+                difficulty: active_match.game.difficulty.into(),
+                seed: active_match.game.info().seed,
+                moves: active_match.game.moves().to_vec(),
+                participants: active_match.participants.clone(),
+                accounts: active_match.accounts.clone(),
+                scores: active_match.scores.clone(),
+                last_player: active_match.last_player.clone(),
+                current_player: active_match.current_player.clone(),
+                eliminated: active_match.eliminated.clone(),
+                reveals_this_turn: active_match.reveals_this_turn,
+            }),
+            RoomMatchState::Won { final_snapshot } => MatchSnapshot::Won {
+                final_snapshot: final_snapshot.clone(),
+            },
+            RoomMatchState::NoWinner { final_snapshot } => MatchSnapshot::NoWinner {
+                final_snapshot: final_snapshot.clone(),
+            },
+        };
+        RoomSnapshot {
+            code: self.code.clone(),
+            owner: self.owner.clone(),
+            seats,
+            settings: self.settings.clone(),
+            banned: self.banned.clone(),
+            match_state,
+        }
+    }
+
+    /// Whether nobody is seated or can reclaim a seat, so the room has no reason to run.
+    pub fn is_abandoned(&self) -> bool {
+        self.players.is_empty() && self.reserved.is_empty()
+    }
+
+    /// Writes the room's snapshot if it changed since the last write.
+    async fn persist(&mut self) {
+        let Some(store) = self.store.clone() else {
+            return;
+        };
+        let contents = match serde_json::to_vec(&self.snapshot()) {
+            Ok(contents) => contents,
+            Err(error) => {
+                warn!(
+                    target: "multisweeper.room.snapshot_failed",
+                    room_code = %self.code,
+                    error = %error,
+                    "room snapshot could not be serialized"
+                );
+                return;
+            }
+        };
+        if contents == self.persisted {
+            return;
+        }
+        let code = self.code.clone();
+        // The write is blocking file io, so keep it off the runtime's worker threads.
+        let write = task::spawn_blocking(move || store.save(&code, &contents).map(|()| contents))
+            .await
+            .unwrap_or_else(|error| Err(io::Error::other(error).into()));
+        match write {
+            Ok(contents) => self.persisted = contents,
+            Err(error) => warn!(
+                target: "multisweeper.room.snapshot_failed",
+                room_code = %self.code,
+                error = %error,
+                "room snapshot could not be written"
+            ),
+        }
+    }
+
+    /// Sends every player away, leaving the room empty so its task ends.
+    async fn close(&mut self, reason: String) {
+        info!(
+            target: "multisweeper.room.closed_by_operator",
+            room_code = %self.code,
+            player_count = self.players.len(),
+            reason = %reason,
+            "room closed by an operator"
+        );
+        self.reserved.clear();
+        self.reservation_deadline = None;
+        for (_, player) in self.players.drain() {
+            let _ = player
+                .address
+                .send(SessionMessage::Broadcast(SessionEvent::RoomRemoved {
+                    reason: reason.clone(),
+                }))
+                .await;
+        }
+    }
+
+    fn forget_snapshot(&self) {
+        if let Some(store) = &self.store
+            && let Err(error) = store.remove(&self.code)
+        {
+            warn!(
+                target: "multisweeper.room.snapshot_failed",
+                room_code = %self.code,
+                error = %error,
+                "room snapshot could not be removed"
+            );
         }
     }
 
+    fn rename_player(&mut self, from: &PlayerId, to: &PlayerId) {
+        if from == to {
+            return;
+        }
+        if self.owner.as_ref() == Some(from) {
+            self.owner = Some(to.clone());
+        }
+        if let RoomMatchState::Playing(active_match) = &mut self.match_state {
+            for participant in &mut active_match.participants {
+                if participant == from {
+                    *participant = to.clone();
+                }
+            }
+            if let Some(account) = active_match.accounts.remove(from) {
+                active_match.accounts.insert(to.clone(), account);
+            }
+            if let Some(score) = active_match.scores.remove(from) {
+                active_match.scores.insert(to.clone(), score);
+            }
+            if &active_match.current_player == from {
+                active_match.current_player = to.clone();
+            }
+            if active_match.last_player.as_ref() == Some(from) {
+                active_match.last_player = Some(to.clone());
+            }
+            for eliminated in &mut active_match.eliminated {
+                if eliminated == from {
+                    *eliminated = to.clone();
+                }
+            }
+        }
+    }
+
+    /// Seats `id` in the reserved seat held for `account`, if there is one.
+    fn reclaim_seat(&mut self, id: &PlayerId, addr: &PlayerAddr, account: &Account) -> bool {
+        let Some(seat) = self.reserved.remove(&account.id) else {
+            return false;
+        };
+        self.rename_player(&account.id, id);
+        let seat_order = self.take_seat();
+        self.players.insert(
+            id.clone(),
+            PlayerRecord {
+                address: addr.clone(),
+                account: Some(account.clone()),
+                state: seat.state,
+                seat: seat_order,
+                ready: false,
+            },
+        );
+        if self.owner.is_none() {
+            self.owner = Some(id.clone());
+        }
+        if self.reserved.is_empty() {
+            self.reservation_deadline = None;
+        }
+        info!(
+            target: "multisweeper.room.seat_reclaimed",
+            room_code = %self.code,
+            player_id = %id,
+            account_id = %account.id,
+            "player reclaimed restored seat"
+        );
+        if let RoomMatchState::Playing(active_match) = &self.match_state
+            && !self.players.contains_key(&active_match.current_player)
+            && self.has_active_players()
+        {
+            let _ = self.move_to_next_player();
+        }
+        true
+    }
+
+    fn expire_reservations(&mut self) {
+        let expired = self.reserved.len();
+        self.reserved.clear();
+        self.reservation_deadline = None;
+        if self
+            .owner
+            .as_ref()
+            .is_some_and(|owner| !self.players.contains_key(owner))
+        {
+            self.hand_over_ownership();
+        }
+        if let RoomMatchState::Playing(active_match) = &self.match_state
+            && !self.players.contains_key(&active_match.current_player)
+        {
+            if self.has_active_players() {
+                let _ = self.move_to_next_player();
+            } else {
+                let _ = self.finish_without_winner();
+            }
+        }
+        info!(
+            target: "multisweeper.room.reservations_expired",
+            room_code = %self.code,
+            expired_seats = expired,
+            "restored seats expired"
+        );
+    }
+
     fn start_game(
         &mut self,
         requestor_id: PlayerId,
@@ -130,29 +565,317 @@ impl Room {
         if Some(requestor_id.clone()) != self.owner {
             return Err(RoomError::NotOwner);
         }
-        if !matches!(self.match_state, RoomMatchState::Waiting) {
+        self.begin_match(difficulty)
+    }
+
+    /// Starts a match among the ready players, ordered by the room's turn order setting. A
+    /// finished match can be followed by another one.
+    fn begin_match(&mut self, difficulty: GameDifficulty) -> Result<(), RoomError> {
+        if matches!(self.match_state, RoomMatchState::Playing(_)) {
             return Err(RoomError::GameAlreadyStarted);
         }
-        let game = Game::new(difficulty, random())?;
-        let participants = self.players.keys().cloned().collect();
+        self.ensure_enough_ready()?;
+        let seed = random();
+        let game = Game::new(difficulty, seed)?;
+        let participants = self.turn_order(seed);
+        let current_player = participants[0].clone();
+        let accounts = self
+            .players
+            .iter()
+            .filter(|(_, player)| player.ready)
+            .filter_map(|(id, player)| Some((id.clone(), player.account.clone()?)))
+            .collect();
         for player in self.players.values_mut() {
-            player.state = PlayerState::Playing;
+            player.state = if player.ready {
+                PlayerState::Playing
+            } else {
+                PlayerState::Spectator
+            };
+            player.ready = false;
         }
-        self.match_state = RoomMatchState::Playing(PlayingMatch {
+        self.countdown_deadline = None;
+        self.match_state = RoomMatchState::Playing(Box::new(PlayingMatch {
             game,
             participants,
+            accounts,
+            scores: HashMap::new(),
             last_player: None,
-            current_player: requestor_id.clone(),
-        });
+            current_player: current_player.clone(),
+            eliminated: Vec::new(),
+            reveals_this_turn: 0,
+        }));
+        METRICS.matches_started.inc();
         info!(
             target: "multisweeper.room.match_started",
             room_code = %self.code,
-            player_id = %requestor_id,
+            player_id = %current_player,
             "match started"
         );
         Ok(())
     }
 
+    /// The ready players in the order they will take turns.
+    fn turn_order(&self, seed: u64) -> Vec<PlayerId> {
+        let mut ready = self
+            .players
+            .iter()
+            .filter(|(_, player)| player.ready)
+            .collect::<Vec<_>>();
+        ready.sort_by_key(|(_, player)| player.seat);
+        let mut order = ready
+            .into_iter()
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>();
+        match self.settings.turn_order {
+            TurnOrder::JoinOrder => {}
+            TurnOrder::Random => order.shuffle(&mut StdRng::seed_from_u64(seed)),
+            TurnOrder::LoserFirst => order.sort_by_key(|id| {
+                self.last_losers
+                    .iter()
+                    .position(|loser| loser == id)
+                    .unwrap_or(usize::MAX)
+            }),
+        }
+        order
+    }
+
+    /// Players still in the match who play after the current player, in turn order.
+    fn turn_queue(&self, active_match: &PlayingMatch) -> Vec<PlayerId> {
+        let participants = &active_match.participants;
+        let Some(current_index) = participants
+            .iter()
+            .position(|id| id == &active_match.current_player)
+        else {
+            return Vec::new();
+        };
+        (1..participants.len())
+            .map(|offset| &participants[(current_index + offset) % participants.len()])
+            .filter(|id| {
+                self.players
+                    .get(*id)
+                    .is_some_and(|player| player.state == PlayerState::Playing)
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn ensure_enough_ready(&self) -> Result<(), RoomError> {
+        let ready = self.players.values().filter(|player| player.ready).count();
+        let required = match self.settings.min_ready {
+            0 => self.players.len(),
+            min_ready => usize::from(min_ready),
+        }
+        .max(1);
+        if ready < required {
+            return Err(RoomError::NotEnoughReady { ready, required });
+        }
+        Ok(())
+    }
+
+    fn set_ready(&mut self, id: &PlayerId, ready: bool) -> Result<(), RoomError> {
+        if matches!(self.match_state, RoomMatchState::Playing(_)) {
+            return Err(RoomError::GameAlreadyStarted);
+        }
+        let player = self
+            .players
+            .get_mut(id)
+            .ok_or_else(|| RoomError::NoPlayerFound(id.clone()))?;
+        player.ready = ready;
+        debug!(
+            target: "multisweeper.room.ready_changed",
+            room_code = %self.code,
+            player_id = %id,
+            ready,
+            "player readiness changed"
+        );
+        Ok(())
+    }
+
+    /// Starts or cancels the auto-start countdown to match the room's readiness, returning the
+    /// event to broadcast when it changes.
+    fn update_countdown(&mut self) -> Option<SessionEvent> {
+        let should_run = !matches!(self.match_state, RoomMatchState::Playing(_))
+            && self.settings.auto_start_secs > 0
+            && self.ensure_enough_ready().is_ok();
+        match (self.countdown_deadline, should_run) {
+            (None, true) => {
+                let starts_in_secs = self.settings.auto_start_secs;
+                self.countdown_deadline =
+                    Some(Instant::now() + Duration::from_secs(starts_in_secs.into()));
+                info!(
+                    target: "multisweeper.room.countdown_started",
+                    room_code = %self.code,
+                    starts_in_secs,
+                    "auto-start countdown started"
+                );
+                Some(SessionEvent::CountdownStarted { starts_in_secs })
+            }
+            (Some(_), false) => {
+                self.countdown_deadline = None;
+                info!(
+                    target: "multisweeper.room.countdown_cancelled",
+                    room_code = %self.code,
+                    "auto-start countdown cancelled"
+                );
+                Some(SessionEvent::CountdownCancelled)
+            }
+            _ => None,
+        }
+    }
+
+    async fn auto_start(&mut self) -> Result<(), Vec<RoomError>> {
+        self.countdown_deadline = None;
+        let difficulty = self.settings.auto_start_difficulty.into();
+        let event = match self.begin_match(difficulty) {
+            Ok(()) => SessionEvent::GameStarted,
+            Err(error) => {
+                warn!(
+                    target: "multisweeper.room.auto_start_failed",
+                    room_code = %self.code,
+                    error_type = room_error_name(&error),
+                    "auto-start countdown ended without starting a match"
+                );
+                SessionEvent::CountdownCancelled
+            }
+        };
+        let mut errs = Vec::new();
+        if let Err(mut send_errors) = self.broadcast_message(event, None).await {
+            errs.append(&mut send_errors);
+        }
+        if let Err(mut send_errors) = self.broadcast_state(None).await {
+            errs.append(&mut send_errors);
+        }
+        match errs.len() {
+            0 => Ok(()),
+            _ => Err(errs),
+        }
+    }
+
+    fn configure(
+        &mut self,
+        requestor_id: &PlayerId,
+        changes: RoomSettingsUpdate,
+    ) -> Result<(), RoomError> {
+        if self.owner.as_ref() != Some(requestor_id) {
+            return Err(RoomError::NotOwner);
+        }
+        if changes
+            .spectator_delay_secs
+            .is_some_and(|delay| delay > MAX_SPECTATOR_DELAY_SECS)
+        {
+            return Err(RoomError::InvalidSettings(format!(
+                "spectator delay must be at most {MAX_SPECTATOR_DELAY_SECS} seconds"
+            )));
+        }
+        if let Some(TurnPolicy::Reveals { per_turn: 0 }) = changes.turn_policy {
+            return Err(RoomError::InvalidSettings(
+                "reveals per turn must be at least 1".to_string(),
+            ));
+        }
+        if changes
+            .auto_start_secs
+            .is_some_and(|secs| secs > MAX_AUTO_START_SECS)
+        {
+            return Err(RoomError::InvalidSettings(format!(
+                "auto-start countdown must be at most {MAX_AUTO_START_SECS} seconds"
+            )));
+        }
+        let was_delayed = self.settings.spectator_delay_secs > 0;
+        if let Some(hide) = changes.hide_flags_from_spectators {
+            self.settings.hide_flags_from_spectators = hide;
+        }
+        if let Some(delay) = changes.spectator_delay_secs {
+            self.settings.spectator_delay_secs = delay;
+        }
+        if let Some(min_ready) = changes.min_ready {
+            self.settings.min_ready = min_ready;
+        }
+        if let Some(secs) = changes.auto_start_secs {
+            self.settings.auto_start_secs = secs;
+        }
+        if let Some(difficulty) = changes.auto_start_difficulty {
+            self.settings.auto_start_difficulty = difficulty;
+        }
+        if let Some(turn_order) = changes.turn_order {
+            self.settings.turn_order = turn_order;
+        }
+        if let Some(turn_policy) = changes.turn_policy {
+            self.settings.turn_policy = turn_policy;
+        }
+        if let Some(private) = changes.private {
+            self.settings.private = private;
+        }
+        if self.settings.spectator_delay_secs == 0 {
+            self.spectator_feed.clear();
+        } else if !was_delayed {
+            self.spectator_view = self.match_view();
+        }
+        info!(
+            target: "multisweeper.room.settings_changed",
+            room_code = %self.code,
+            player_id = %requestor_id,
+            hide_flags_from_spectators = self.settings.hide_flags_from_spectators,
+            spectator_delay_secs = self.settings.spectator_delay_secs,
+            min_ready = self.settings.min_ready,
+            auto_start_secs = self.settings.auto_start_secs,
+            turn_order = ?self.settings.turn_order,
+            turn_policy = ?self.settings.turn_policy,
+            "room settings changed"
+        );
+        Ok(())
+    }
+
+    /// Whether `id` is in the room during a match without a turn in it.
+    fn is_watching(&self, id: &PlayerId) -> bool {
+        matches!(self.match_state, RoomMatchState::Playing(_))
+            && self
+                .players
+                .get(id)
+                .is_some_and(|player| player.state != PlayerState::Playing)
+    }
+
+    /// What watchers see of the match once the room's spectator settings are applied.
+    fn spectator_match_view(&self) -> MatchView {
+        let mut view = match self.settings.spectator_delay_secs {
+            0 => self.match_view(),
+            _ => self.spectator_view.clone(),
+        };
+        if self.settings.hide_flags_from_spectators
+            && let Some(game) = &mut view.game
+        {
+            hide_flags(game);
+        }
+        view
+    }
+
+    /// Holds back `view` for the spectator delay while a match is running; outside a match
+    /// there is nothing to coach, so spectators catch up immediately.
+    fn queue_spectator_view(&mut self, view: &MatchView) {
+        match (&self.match_state, self.settings.spectator_delay_secs) {
+            (RoomMatchState::Playing(_), 0) => {}
+            (RoomMatchState::Playing(_), delay) => {
+                let due = Instant::now() + Duration::from_secs(delay.into());
+                self.spectator_feed.push_back((due, view.clone()));
+            }
+            _ => {
+                self.spectator_feed.clear();
+                self.spectator_view = view.clone();
+            }
+        }
+    }
+
+    fn release_spectator_feed(&mut self) {
+        let now = Instant::now();
+        while let Some((due, _)) = self.spectator_feed.front() {
+            if *due > now {
+                break;
+            }
+            if let Some((_, view)) = self.spectator_feed.pop_front() {
+                self.spectator_view = view;
+            }
+        }
+    }
+
     pub fn code(&self) -> &RoomCode {
         &self.code
     }
@@ -161,12 +884,21 @@ impl Room {
         self.addr.clone()
     }
 
+    pub fn control_handle(&self) -> RoomControlAddr {
+        self.control_addr.clone()
+    }
+
     fn get_player_queue(&self) -> Vec<PlayerView> {
         self.players
             .iter()
             .map(|(id, player)| PlayerView {
                 id: id.clone(),
+                display_name: player
+                    .account
+                    .as_ref()
+                    .map(|account| account.display_name.clone()),
                 state: player.state.clone(),
+                ready: player.ready,
             })
             .collect()
     }
@@ -181,8 +913,9 @@ impl Room {
                 state: ProtocolMatchState::Playing {
                     last_player: active_match.last_player.clone(),
                     current_player: active_match.current_player.clone(),
+                    turn_queue: self.turn_queue(active_match),
                 },
-                game: Some(active_match.game.snapshot().clone()),
+                game: Some(active_match.game.snapshot()),
             },
             RoomMatchState::Won { final_snapshot } => MatchView {
                 state: ProtocolMatchState::Won,
@@ -195,24 +928,165 @@ impl Room {
         }
     }
 
-    pub fn state(&self) -> Result<RoomState, RoomError> {
-        Ok(RoomState {
-            code: self.code().to_string(),
-            players: self.get_player_queue(),
-            owner: self.owner.to_owned(),
-            match_state: self.match_view(),
-        })
+    pub fn state(&self) -> Result<RoomState, RoomError> {
+        Ok(RoomState {
+            code: self.code().to_string(),
+            players: self.get_player_queue(),
+            owner: self.owner.to_owned(),
+            settings: self.settings.clone(),
+            match_state: self.match_view(),
+        })
+    }
+
+    pub fn summary(&self) -> RoomSummary {
+        let (match_state, difficulty) = match &self.match_state {
+            RoomMatchState::Waiting => (MatchPhase::Waiting, self.settings.auto_start_difficulty),
+            RoomMatchState::Playing(active_match) => {
+                (MatchPhase::Playing, active_match.game.difficulty.into())
+            }
+            RoomMatchState::Won { .. } | RoomMatchState::NoWinner { .. } => {
+                (MatchPhase::Finished, self.settings.auto_start_difficulty)
+            }
+        };
+        RoomSummary {
+            code: self.code.clone(),
+            player_count: self.players.len() + self.reserved.len(),
+            capacity: self.max_players,
+            owner_name: self
+                .owner
+                .as_ref()
+                .and_then(|owner| self.players.get(owner))
+                .and_then(|player| player.account.as_ref())
+                .map(|account| account.display_name.clone()),
+            mode: MatchMode::TurnBased,
+            difficulty,
+            match_state,
+            private: self.settings.private,
+        }
+    }
+
+    /// Sends the registry this room's summary if it changed since the last one sent.
+    fn publish_summary(&mut self) {
+        let summary = self.summary();
+        if self.published_summary.as_ref() == Some(&summary) {
+            return;
+        }
+        self.published_summary = Some(summary.clone());
+        self.summary_revision += 1;
+        let revision = self.summary_revision;
+        let registry = self.registry.clone();
+        // Spawned so the room never waits on the registry, which may be waiting on rooms.
+        tokio::spawn(async move {
+            let _ = registry
+                .send(RegistryMessage::RoomUpdated { summary, revision })
+                .await;
+        });
+    }
+
+    fn take_seat(&mut self) -> u64 {
+        self.next_seat += 1;
+        self.next_seat
+    }
+
+    /// Passes ownership to the longest-seated player, or leaves the room ownerless if it is empty.
+    fn hand_over_ownership(&mut self) {
+        self.owner = self
+            .players
+            .iter()
+            .min_by_key(|(_, player)| player.seat)
+            .map(|(id, _)| id.clone());
+        if let Some(owner) = &self.owner {
+            info!(
+                target: "multisweeper.room.owner_changed",
+                room_code = %self.code,
+                player_id = %owner,
+                "room ownership handed over"
+            );
+        }
+    }
+
+    /// Whether a newcomer would exceed the player limit; reclaiming a reserved seat never does.
+    fn is_full(&self, account: Option<&Account>) -> bool {
+        let reclaims = account.is_some_and(|account| self.reserved.contains_key(&account.id));
+        !reclaims && self.players.len() + self.reserved.len() >= self.max_players
+    }
+
+    fn is_banned(&self, id: &PlayerId, account: Option<&Account>) -> bool {
+        self.banned.contains(id) || account.is_some_and(|account| self.banned.contains(&account.id))
+    }
+
+    /// Removes `target` from the room, optionally banning them, and returns their address.
+    async fn kick(
+        &mut self,
+        requestor_id: &PlayerId,
+        target: &PlayerId,
+        ban: bool,
+    ) -> Result<PlayerAddr, RoomError> {
+        if self.owner.as_ref() != Some(requestor_id) {
+            return Err(RoomError::NotOwner);
+        }
+        if requestor_id == target {
+            return Err(RoomError::CannotTargetSelf);
+        }
+        let player = self
+            .players
+            .get(target)
+            .ok_or_else(|| RoomError::NoPlayerFound(target.clone()))?;
+        if ban {
+            if let Some(account) = &player.account {
+                self.banned.insert(account.id.clone());
+            }
+            self.banned.insert(target.clone());
+        }
+        let addr = self.drop_player(target).await?;
+        info!(
+            target: "multisweeper.room.player_kicked",
+            room_code = %self.code,
+            player_id = %target,
+            banned = ban,
+            "player kicked from room"
+        );
+        Ok(addr)
+    }
+
+    fn transfer_owner(
+        &mut self,
+        requestor_id: &PlayerId,
+        target: &PlayerId,
+    ) -> Result<(), RoomError> {
+        if self.owner.as_ref() != Some(requestor_id) {
+            return Err(RoomError::NotOwner);
+        }
+        if requestor_id == target {
+            return Err(RoomError::CannotTargetSelf);
+        }
+        if !self.players.contains_key(target) {
+            return Err(RoomError::NoPlayerFound(target.clone()));
+        }
+        self.owner = Some(target.clone());
+        info!(
+            target: "multisweeper.room.owner_changed",
+            room_code = %self.code,
+            player_id = %target,
+            previous_owner = %requestor_id,
+            "room ownership transferred"
+        );
+        Ok(())
     }
 
-    fn register_player(&mut self, id: PlayerId, addr: PlayerAddr) {
+    fn register_player(&mut self, id: PlayerId, addr: PlayerAddr, account: Option<Account>) {
         if self.owner.is_none() {
             self.owner = Some(id.clone());
         }
+        let seat = self.take_seat();
         self.players.insert(
             id.clone(),
             PlayerRecord {
                 address: addr,
+                account,
                 state: PlayerState::Spectator,
+                seat,
+                ready: false,
             },
         );
         info!(
@@ -220,6 +1094,7 @@ impl Room {
             room_code = %self.code,
             player_id = %id,
             player_state = "spectator",
+            mid_match = !matches!(self.match_state, RoomMatchState::Waiting),
             "player joined room"
         );
     }
@@ -284,6 +1159,7 @@ impl Room {
         if let RoomMatchState::Playing(active_match) = &mut self.match_state {
             active_match.last_player = Some(current_player);
             active_match.current_player = next_player;
+            active_match.reveals_this_turn = 0;
             info!(
                 target: "multisweeper.room.turn_changed",
                 room_code = %self.code,
@@ -295,12 +1171,39 @@ impl Room {
         Ok(())
     }
 
+    /// Whether `action`, which left the match running, passes the turn under the room's turn policy.
+    fn turn_ends(&self, action: GameAction, snapshot: &GameSnapshot) -> bool {
+        let GameAction::Reveal { x, y } = action else {
+            return self.settings.turn_policy == TurnPolicy::EveryAction;
+        };
+        if matches!(snapshot.action_result, GameActionResult::Stalled) {
+            return self.settings.turn_policy == TurnPolicy::EveryAction;
+        }
+        match self.settings.turn_policy {
+            TurnPolicy::EveryAction | TurnPolicy::FreeFlags => true,
+            TurnPolicy::Reveals { per_turn } => match &self.match_state {
+                RoomMatchState::Playing(active_match) => active_match.reveals_this_turn >= per_turn,
+                _ => true,
+            },
+            TurnPolicy::UntilNumber => matches!(
+                snapshot
+                    .board
+                    .get(usize::from(y))
+                    .and_then(|row| row.get(usize::from(x))),
+                Some(GameCell::VisibleCell(adjacent)) if *adjacent > 0
+            ),
+        }
+    }
+
     fn mark_player_eliminated(&mut self, id: &PlayerId) -> Result<(), RoomError> {
         let player = self
             .players
             .get_mut(id)
             .ok_or_else(|| RoomError::NoPlayerFound(id.clone()))?;
         player.state = PlayerState::Eliminated;
+        if let RoomMatchState::Playing(active_match) = &mut self.match_state {
+            active_match.eliminated.push(id.clone());
+        }
         info!(
             target: "multisweeper.room.player_eliminated",
             room_code = %self.code,
@@ -323,8 +1226,10 @@ impl Room {
             return Err(RoomError::GameEnded);
         };
         active_match.game.lose_game();
+        self.report_match(&active_match, MatchOutcome::NoWinner);
+        self.last_losers = active_match.eliminated.clone();
         self.match_state = RoomMatchState::NoWinner {
-            final_snapshot: active_match.game.snapshot().clone(),
+            final_snapshot: active_match.game.snapshot(),
         };
         info!(
             target: "multisweeper.room.match_finished",
@@ -335,9 +1240,77 @@ impl Room {
         Ok(())
     }
 
+    fn finish_with_winner(&mut self) -> Result<(), RoomError> {
+        let match_state = std::mem::replace(&mut self.match_state, RoomMatchState::Waiting);
+        let RoomMatchState::Playing(active_match) = match_state else {
+            self.match_state = match_state;
+            return Err(RoomError::GameEnded);
+        };
+        self.report_match(&active_match, MatchOutcome::Won);
+        self.last_losers = active_match.eliminated.clone();
+        self.match_state = RoomMatchState::Won {
+            final_snapshot: active_match.game.snapshot(),
+        };
+        info!(
+            target: "multisweeper.room.match_finished",
+            room_code = %self.code,
+            outcome = "won",
+            "match finished"
+        );
+        Ok(())
+    }
+
+    /// Hands the finished match to the registry for the stats store without blocking the room.
+    fn report_match(&self, active_match: &PlayingMatch, outcome: MatchOutcome) {
+        METRICS.match_finished(outcome);
+        let info = active_match.game.info();
+        let participants = active_match
+            .participants
+            .iter()
+            .map(|id| {
+                let player = self.players.get(id);
+                let account = player
+                    .and_then(|player| player.account.as_ref())
+                    .or_else(|| active_match.accounts.get(id));
+                MatchParticipant {
+                    player_id: id.clone(),
+                    account_id: account.map(|account| account.id.clone()),
+                    display_name: account.map(|account| account.display_name.clone()),
+                    won: outcome == MatchOutcome::Won
+                        && player.is_some_and(|player| player.state == PlayerState::Playing),
+                    score: active_match.scores.get(id).copied().unwrap_or(0),
+                }
+            })
+            .collect();
+        let record = MatchRecord {
+            room_code: self.code.clone(),
+            mode: MatchMode::TurnBased,
+            difficulty: active_match.game.difficulty.into(),
+            width: info.width,
+            height: info.height,
+            mines: info.number_of_mines,
+            seed: info.seed,
+            duration_ms: active_match
+                .game
+                .elapsed()
+                .as_millis()
+                .try_into()
+                .unwrap_or(u64::MAX),
+            outcome,
+            finished_at: unix_millis(),
+            participants,
+        };
+        let registry = self.registry.clone();
+        tokio::spawn(async move {
+            let _ = registry.send(RegistryMessage::RecordMatch(record)).await;
+        });
+    }
+
     #[tracing::instrument(name = "room.lifecycle", skip_all, fields(room_code = %self.code))]
     pub async fn handle_connection(mut self) -> Result<()> {
+        METRICS.rooms.inc();
         let result = self.event_loop().await;
+        METRICS.rooms.dec();
         if let Err(error) = &result {
             error!(
                 target: "multisweeper.room.failed",
@@ -346,13 +1319,31 @@ impl Room {
                 "room task terminated with an error"
             );
         }
+        // Drop the mailbox first so the registry can tell this room has finished.
+        let (code, registry) = (self.code.clone(), self.registry.clone());
+        drop(self);
+        let _ = registry.send(RegistryMessage::RoomClosed(code)).await;
         result
     }
 
     async fn event_loop(&mut self) -> Result<()> {
         loop {
+            let deadline = self.reservation_deadline;
+            let feed_due = self.spectator_feed.front().map(|(due, _)| *due);
+            let countdown = self.countdown_deadline;
             let event = tokio::select! {
-                msg = self.mailbox.recv() => RoomEvent::Session(msg)
+                msg = self.mailbox.recv() => RoomEvent::Session(msg),
+                Some(control) = self.control_mailbox.recv() => RoomEvent::Control(control),
+                _ = sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                    RoomEvent::ReservationExpired
+                }
+                _ = sleep_until(feed_due.unwrap_or_else(Instant::now)), if feed_due.is_some() => {
+                    RoomEvent::SpectatorFeedDue
+                }
+                _ = sleep_until(countdown.unwrap_or_else(Instant::now)), if countdown.is_some() => {
+                    RoomEvent::CountdownElapsed
+                }
+                _ = shutdown::requested(self.shutdown.as_mut()) => RoomEvent::Shutdown,
             };
 
             match event {
@@ -367,14 +1358,61 @@ impl Room {
                         }
                     }
                 }
+                RoomEvent::ReservationExpired => {
+                    self.expire_reservations();
+                    if let Err(errs) = self.broadcast_state(None).await {
+                        let mut remainder = self.resolve_mailbox_errs(errs).await;
+                        if let Some(error) = remainder.pop() {
+                            return Err(error.into());
+                        }
+                    }
+                }
+                RoomEvent::SpectatorFeedDue => {
+                    self.release_spectator_feed();
+                    if let Err(errs) = self.broadcast_spectator_state().await {
+                        let mut remainder = self.resolve_mailbox_errs(errs).await;
+                        if let Some(error) = remainder.pop() {
+                            return Err(error.into());
+                        }
+                    }
+                }
+                RoomEvent::CountdownElapsed => {
+                    if let Err(errs) = self.auto_start().await {
+                        let mut remainder = self.resolve_mailbox_errs(errs).await;
+                        if let Some(error) = remainder.pop() {
+                            return Err(error.into());
+                        }
+                    }
+                }
+                RoomEvent::Control(RoomControl::Inspect(reply)) => {
+                    if let Ok(state) = self.state() {
+                        let _ = reply.send(state);
+                    }
+                }
+                RoomEvent::Control(RoomControl::Close { reason }) => self.close(reason).await,
+                RoomEvent::Shutdown => {
+                    // Keep the snapshot so the room comes back when the server restarts.
+                    self.persist().await;
+                    info!(
+                        target: "multisweeper.room.shutdown",
+                        room_code = %self.code,
+                        player_count = self.players.len(),
+                        "room task stopping for server shutdown"
+                    );
+                    return Ok(());
+                }
             }
 
-            if self.players.len() == 0 {
+            self.persist().await;
+            self.publish_summary();
+
+            if self.players.len() == 0 && self.reserved.is_empty() {
                 info!(
                     target: "multisweeper.room.room_closed",
                     room_code = %self.code,
                     "room task terminating gracefully due to no players"
                 );
+                self.forget_snapshot();
                 return Ok(());
             }
         }
@@ -420,6 +1458,9 @@ impl Room {
     }
 
     async fn handle_mailbox(&mut self, msg: RoomMessage) -> Result<(), Vec<RoomError>> {
+        METRICS
+            .room_mailbox_latency
+            .observe(msg.sent_at.elapsed().as_secs_f64());
         let player_id = msg.id.clone();
         let request = msg.request;
         let reply_to = request.reply_to.clone();
@@ -432,25 +1473,57 @@ impl Room {
         );
         let mut errs = Vec::new();
         let mut correlated_state_for = None;
+        let mut state_changed = true;
+        let mut replay_chat_to = None;
         match msg.command {
-            PlayerCommand::Join => {
-                if matches!(self.match_state, RoomMatchState::Waiting) {
-                    self.register_player(player_id.clone(), reply_to.clone());
-                    correlated_state_for = Some((player_id.clone(), request.clone()));
-                } else {
-                    let _ = reply_to
-                        .send(SessionMessage::Reply {
-                            request_id: request.message_id.clone(),
-                            message: SessionEvent::RoomJoinRejected {
-                                error: ClientError::new(
-                                    ErrorCode::GameAlreadyStarted,
-                                    "game has already started",
-                                ),
-                            },
-                        })
-                        .await;
+            PlayerCommand::Join { account, .. } if self.is_banned(&player_id, account.as_ref()) => {
+                let _ = reply_to
+                    .send(SessionMessage::Reply {
+                        request_id: request.message_id.clone(),
+                        message: SessionEvent::RoomJoinRejected {
+                            error: RoomError::Banned(player_id.clone()).client_error(),
+                        },
+                    })
+                    .await;
+            }
+            PlayerCommand::Join { account, .. } if self.is_full(account.as_ref()) => {
+                let _ = reply_to
+                    .send(SessionMessage::Reply {
+                        request_id: request.message_id.clone(),
+                        message: SessionEvent::RoomJoinRejected {
+                            error: RoomError::RoomFull.client_error(),
+                        },
+                    })
+                    .await;
+            }
+            PlayerCommand::Join { account, ready } => {
+                let reclaimed = account
+                    .as_ref()
+                    .is_some_and(|account| self.reclaim_seat(&player_id, &reply_to, account));
+                if !reclaimed {
+                    self.register_player(player_id.clone(), reply_to.clone(), account);
+                }
+                if ready {
+                    let _ = self.set_ready(&player_id, true);
                 }
+                correlated_state_for = Some((player_id.clone(), request.clone()));
+                replay_chat_to = Some(player_id.clone());
             }
+            PlayerCommand::Identify { account } => match self.players.get_mut(&player_id) {
+                Some(player) => {
+                    player.account = Some(account);
+                    correlated_state_for = Some((player_id.clone(), request.clone()));
+                }
+                None => {
+                    self.send_player_error(
+                        &player_id,
+                        &reply_to,
+                        RoomError::NoPlayerFound(player_id.clone()),
+                        &request,
+                    )
+                    .await;
+                }
+            },
             PlayerCommand::Leave => {
                 let addr = match self.drop_player(&player_id).await {
                     Ok(addr) => addr,
@@ -490,6 +1563,7 @@ impl Room {
                 };
             }
             PlayerCommand::GameAction { action } => {
+                METRICS.game_actions.inc();
                 let result = self.ensure_can_play(&player_id).and_then(|()| {
                     let RoomMatchState::Playing(active_match) = &mut self.match_state else {
                         return Err(match self.match_state {
@@ -500,11 +1574,22 @@ impl Room {
                             RoomMatchState::Playing(_) => unreachable!(),
                         });
                     };
-                    active_match
+                    let revealed_before = active_match.game.snapshot().revealed;
+                    let snapshot = active_match
                         .game
                         .handle_action(action)
                         .cloned()
-                        .map_err(RoomError::from)
+                        .map_err(RoomError::from)?;
+                    let revealed = snapshot.revealed.saturating_sub(revealed_before);
+                    *active_match.scores.entry(player_id.clone()).or_default() +=
+                        u32::from(revealed);
+                    if matches!(action, GameAction::Reveal { .. })
+                        && !matches!(snapshot.action_result, GameActionResult::Stalled)
+                    {
+                        active_match.reveals_this_turn =
+                            active_match.reveals_this_turn.saturating_add(1);
+                    }
+                    Ok(snapshot)
                 });
 
                 match result {
@@ -522,29 +1607,16 @@ impl Room {
                                 }
                             }
                             GameActionResult::Won => {
-                                let match_state = std::mem::replace(
-                                    &mut self.match_state,
-                                    RoomMatchState::Waiting,
-                                );
-                                if let RoomMatchState::Playing(active_match) = match_state {
-                                    self.match_state = RoomMatchState::Won {
-                                        final_snapshot: active_match.game.snapshot().clone(),
-                                    };
-                                    info!(
-                                        target: "multisweeper.room.match_finished",
-                                        room_code = %self.code,
-                                        outcome = "won",
-                                        "match finished"
-                                    );
-                                } else {
-                                    self.match_state = match_state;
-                                    errs.push(RoomError::GameEnded);
+                                if let Err(error) = self.finish_with_winner() {
+                                    errs.push(error);
                                 }
                             }
                             GameActionResult::Applied
                             | GameActionResult::Stalled
                             | GameActionResult::Started => {
-                                if let Err(error) = self.move_to_next_player() {
+                                if self.turn_ends(action, &snapshot)
+                                    && let Err(error) = self.move_to_next_player()
+                                {
                                     errs.push(error);
                                 }
                             }
@@ -577,11 +1649,103 @@ impl Room {
                     correlated_state_for = Some((player_id.clone(), request.clone()));
                 }
             }
+            PlayerCommand::Configure { changes } => match self.configure(&player_id, changes) {
+                Ok(()) => {
+                    correlated_state_for = Some((player_id.clone(), request.clone()));
+                }
+                Err(error) => {
+                    self.send_player_error(&player_id, &reply_to, error, &request)
+                        .await;
+                }
+            },
+            PlayerCommand::Kick { target, ban } => {
+                match self.kick(&player_id, &target, ban).await {
+                    Ok(addr) => {
+                        let reason = if ban {
+                            "banned by owner"
+                        } else {
+                            "kicked by owner"
+                        };
+                        let _ = addr
+                            .send(SessionMessage::Broadcast(SessionEvent::RoomRemoved {
+                                reason: reason.to_string(),
+                            }))
+                            .await;
+                        correlated_state_for = Some((player_id.clone(), request.clone()));
+                    }
+                    Err(error) => {
+                        self.send_player_error(&player_id, &reply_to, error, &request)
+                            .await;
+                    }
+                }
+            }
+            PlayerCommand::TransferOwner { target } => {
+                match self.transfer_owner(&player_id, &target) {
+                    Ok(()) => {
+                        correlated_state_for = Some((player_id.clone(), request.clone()));
+                    }
+                    Err(error) => {
+                        self.send_player_error(&player_id, &reply_to, error, &request)
+                            .await;
+                    }
+                }
+            }
+            PlayerCommand::Ready { ready } => match self.set_ready(&player_id, ready) {
+                Ok(()) => {
+                    correlated_state_for = Some((player_id.clone(), request.clone()));
+                }
+                Err(error) => {
+                    self.send_player_error(&player_id, &reply_to, error, &request)
+                        .await;
+                }
+            },
+            PlayerCommand::Chat { text } => {
+                state_changed = false;
+                match self.post_chat(&player_id, &text) {
+                    Ok(chat) => {
+                        let correlated_message_for = Some((player_id.clone(), request.clone()));
+                        if let Err(mut send_errors) = self
+                            .broadcast_message(
+                                SessionEvent::ChatMessage(chat),
+                                correlated_message_for.as_ref(),
+                            )
+                            .await
+                        {
+                            errs.append(&mut send_errors);
+                        }
+                    }
+                    Err(error) => {
+                        debug!(
+                            target: "multisweeper.room.chat_rejected",
+                            room_code = %self.code,
+                            player_id = %player_id,
+                            error_type = room_error_name(&error),
+                            "chat message rejected"
+                        );
+                        self.send_player_error(&player_id, &reply_to, error, &request)
+                            .await;
+                    }
+                }
+            }
+        }
+
+        if let Some(event) = self.update_countdown()
+            && let Err(mut send_errors) = self.broadcast_message(event, None).await
+        {
+            errs.append(&mut send_errors);
+        }
+
+        if state_changed {
+            match self.broadcast_state(correlated_state_for.as_ref()).await {
+                Ok(()) => (),
+                Err(mut broadcast_errs) => errs.append(&mut broadcast_errs),
+            }
         }
 
-        match self.broadcast_state(correlated_state_for.as_ref()).await {
-            Ok(()) => (),
-            Err(mut broadcast_errs) => errs.append(&mut broadcast_errs),
+        if let Some(id) = replay_chat_to
+            && let Err(error) = self.send_chat_history(&id).await
+        {
+            errs.push(error);
         }
 
         let remainder = self.resolve_mailbox_errs(errs).await;
@@ -599,7 +1763,7 @@ impl Room {
             .ok_or_else(|| RoomError::NoPlayerFound(id.to_string()))?;
 
         if Some(id.clone()) == self.owner {
-            self.owner = self.players.keys().next().cloned();
+            self.hand_over_ownership();
         }
 
         info!(
@@ -641,10 +1805,53 @@ impl Room {
             Ok(state) => state,
             Err(err) => return Err(vec![err]),
         };
+        self.queue_spectator_view(&state.match_state);
 
-        match self.broadcast_message(state.into(), correlated_to).await {
-            Ok(()) => Ok(()),
-            Err(e) => Err(e),
+        let ids = self.players.keys().cloned().collect();
+        self.send_state(state, ids, correlated_to).await
+    }
+
+    /// Sends the current spectator view to everyone watching the match.
+    async fn broadcast_spectator_state(&mut self) -> Result<(), Vec<RoomError>> {
+        let state = match self.state() {
+            Ok(state) => state,
+            Err(err) => return Err(vec![err]),
+        };
+        let ids = self
+            .players
+            .keys()
+            .filter(|id| self.is_watching(id))
+            .cloned()
+            .collect();
+        self.send_state(state, ids, None).await
+    }
+
+    /// Sends `state` to each of `ids`, swapping in the spectator view for anyone watching.
+    async fn send_state(
+        &mut self,
+        state: RoomState,
+        ids: Vec<PlayerId>,
+        correlated_to: Option<&(PlayerId, RequestContext)>,
+    ) -> Result<(), Vec<RoomError>> {
+        let spectator_state = RoomState {
+            match_state: self.spectator_match_view(),
+            ..state.clone()
+        };
+        let mut errors = Vec::new();
+        for id in ids {
+            let state = if self.is_watching(&id) {
+                spectator_state.clone()
+            } else {
+                state.clone()
+            };
+            let message = addressed(state.into(), &id, correlated_to);
+            if let Err(e) = self.send_player(&id, message).await {
+                errors.push(e);
+            }
+        }
+        match errors.len() {
+            0 => Ok(()),
+            _ => Err(errors),
         }
     }
 
@@ -661,13 +1868,7 @@ impl Room {
         let ids: Vec<PlayerId> = self.players.keys().map(ToString::to_string).collect();
         let mut errors = Vec::new();
         for id in ids {
-            let message = match correlated_to.filter(|(target, _)| target == &id) {
-                Some((_, request)) => SessionMessage::Reply {
-                    request_id: request.message_id.clone(),
-                    message: msg.clone(),
-                },
-                None => SessionMessage::Broadcast(msg.clone()),
-            };
+            let message = addressed(msg.clone(), &id, correlated_to);
             match self.send_player(&id, message).await {
                 Ok(()) => continue,
                 Err(e) => {
@@ -707,6 +1908,44 @@ impl Room {
         }
     }
 
+    fn post_chat(&mut self, id: &PlayerId, text: &str) -> Result<ChatMessageView, RoomError> {
+        let player = self
+            .players
+            .get(id)
+            .ok_or_else(|| RoomError::NoPlayerFound(id.clone()))?;
+        let display_name = player
+            .account
+            .as_ref()
+            .map(|account| account.display_name.clone());
+        let chat = self.chat.post(
+            id,
+            display_name,
+            text,
+            Instant::now().into_std(),
+            unix_millis(),
+        )?;
+        debug!(
+            target: "multisweeper.room.chat_posted",
+            room_code = %self.code,
+            player_id = %id,
+            "chat message posted"
+        );
+        Ok(chat)
+    }
+
+    /// Replays recent chat to a player who just took a seat.
+    async fn send_chat_history(&mut self, id: &PlayerId) -> Result<(), RoomError> {
+        let messages = self.chat.history();
+        if messages.is_empty() {
+            return Ok(());
+        }
+        self.send_player(
+            id,
+            SessionMessage::Broadcast(SessionEvent::ChatHistory { messages }),
+        )
+        .await
+    }
+
     async fn send_player_error(
         &mut self,
         id: &PlayerId,
@@ -731,12 +1970,49 @@ impl Room {
 
 fn player_command_name(command: &PlayerCommand) -> &'static str {
     match command {
-        PlayerCommand::Join => "join",
+        PlayerCommand::Join { .. } => "join",
+        PlayerCommand::Identify { .. } => "identify",
         PlayerCommand::Leave => "leave",
         PlayerCommand::StartGame { .. } => "start_game",
         PlayerCommand::GameAction { .. } => "game_action",
         PlayerCommand::GameQuery => "game_query",
+        PlayerCommand::Chat { .. } => "chat",
+        PlayerCommand::Configure { .. } => "configure",
+        PlayerCommand::Kick { .. } => "kick",
+        PlayerCommand::TransferOwner { .. } => "transfer_owner",
+        PlayerCommand::Ready { .. } => "ready",
+    }
+}
+
+/// Wraps `msg` as a reply when `id` sent the request it answers, and as a broadcast otherwise.
+fn addressed(
+    msg: SessionEvent,
+    id: &PlayerId,
+    correlated_to: Option<&(PlayerId, RequestContext)>,
+) -> SessionMessage {
+    match correlated_to.filter(|(target, _)| target == id) {
+        Some((_, request)) => SessionMessage::Reply {
+            request_id: request.message_id.clone(),
+            message: msg,
+        },
+        None => SessionMessage::Broadcast(msg),
+    }
+}
+
+fn hide_flags(game: &mut GameSnapshot) {
+    for cell in game.board.iter_mut().flatten() {
+        if matches!(cell, GameCell::FlaggedCell) {
+            *cell = GameCell::HiddenCell;
+        }
     }
+    game.flags = 0;
+    game.mines_remaining = game.mines.into();
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis().try_into().unwrap_or(u64::MAX))
 }
 
 fn room_error_name(error: &RoomError) -> &'static str {
@@ -753,5 +2029,332 @@ fn room_error_name(error: &RoomError) -> &'static str {
         RoomError::PlayerNotCurrent(_) => "player_not_current",
         RoomError::AllPlayersDropped => "all_players_dropped",
         RoomError::Game(_) => "game_error",
+        RoomError::Chat(_) => "chat_error",
+        RoomError::InvalidSettings(_) => "invalid_settings",
+        RoomError::NotEnoughReady { .. } => "not_enough_ready",
+        RoomError::Banned(_) => "banned",
+        RoomError::CannotTargetSelf => "cannot_target_self",
+        RoomError::RoomFull => "room_full",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use multisweeper_core::{GameAction, GameDifficulty};
+    use tokio::sync::mpsc;
+
+    use crate::{
+        accounts::Account,
+        chat::ChatError,
+        protocol::{
+            room::RoomSettingsUpdate,
+            session::{
+                MatchState as ProtocolMatchState, PlayerState, SessionEvent, TurnOrder, TurnPolicy,
+            },
+        },
+    };
+
+    use super::{Room, RoomError, RoomMatchState};
+
+    fn account(id: &str) -> Account {
+        Account {
+            id: id.to_string(),
+            display_name: id.to_uppercase(),
+        }
+    }
+
+    #[test]
+    fn restored_seats_are_reclaimed_by_their_accounts() {
+        let (registry, _registry_mailbox) = mpsc::channel(10);
+        let (player_addr, _player_mailbox) = mpsc::channel(10);
+        let mut room = Room::new("quiet-otter".to_string(), registry.clone());
+        room.register_player(
+            "P00001".to_string(),
+            player_addr.clone(),
+            Some(account("ada")),
+        );
+        room.register_player("P00002".to_string(), player_addr.clone(), None);
+        for id in ["P00001", "P00002"] {
+            room.set_ready(&id.to_string(), true)
+                .expect("players can ready up");
+        }
+        room.start_game("P00001".to_string(), GameDifficulty::TEST)
+            .expect("owner can start");
+        if let RoomMatchState::Playing(active_match) = &mut room.match_state {
+            active_match
+                .game
+                .handle_action(GameAction::Flag { x: 1, y: 1 })
+                .expect("flag should apply");
+        }
+
+        let mut restored = Room::restore(room.snapshot(), registry.clone(), None)
+            .expect("snapshot should restore");
+        assert!(restored.players.is_empty());
+        assert!(restored.reserved.contains_key("ada"));
+        assert!(!restored.is_abandoned());
+        assert_eq!(restored.owner.as_deref(), Some("ada"));
+
+        assert!(restored.reclaim_seat(&"P00007".to_string(), &player_addr, &account("ada")));
+        assert!(!restored.reclaim_seat(&"P00008".to_string(), &player_addr, &account("bob")));
+        assert_eq!(restored.owner.as_deref(), Some("P00007"));
+        assert_eq!(restored.players["P00007"].state, PlayerState::Playing);
+        assert!(restored.reservation_deadline.is_none());
+        let RoomMatchState::Playing(active_match) = &restored.match_state else {
+            panic!("match should still be in progress");
+        };
+        assert_eq!(active_match.current_player, "P00007");
+        assert_eq!(active_match.game.moves().len(), 1);
+        assert_eq!(active_match.game.snapshot().flags, 1);
+
+        let mut anonymous = Room::new("loud-heron".to_string(), registry.clone());
+        anonymous.register_player("P00003".to_string(), player_addr, None);
+        let restored =
+            Room::restore(anonymous.snapshot(), registry, None).expect("snapshot should restore");
+        assert!(restored.is_abandoned());
+    }
+
+    #[test]
+    fn mid_match_spectators_get_a_delayed_view_without_flags() {
+        let (registry, _registry_mailbox) = mpsc::channel(10);
+        let (player_addr, _player_mailbox) = mpsc::channel(10);
+        let (owner, spectator) = ("P00001".to_string(), "P00002".to_string());
+        let mut room = Room::new("quiet-otter".to_string(), registry);
+        room.register_player(owner.clone(), player_addr.clone(), None);
+        room.set_ready(&owner, true).expect("owner can ready up");
+        room.start_game(owner.clone(), GameDifficulty::TEST)
+            .expect("owner can start");
+        room.register_player(spectator.clone(), player_addr, None);
+        assert!(room.is_watching(&spectator));
+        assert!(!room.is_watching(&owner));
+
+        let delay = RoomSettingsUpdate {
+            spectator_delay_secs: Some(30),
+            ..RoomSettingsUpdate::default()
+        };
+        assert!(matches!(
+            room.configure(&spectator, delay.clone()),
+            Err(RoomError::NotOwner)
+        ));
+        room.configure(&owner, delay).expect("owner can configure");
+        if let RoomMatchState::Playing(active_match) = &mut room.match_state {
+            active_match
+                .game
+                .handle_action(GameAction::Flag { x: 1, y: 1 })
+                .expect("flag should apply");
+        }
+        let live = room.match_view();
+        room.queue_spectator_view(&live);
+        let delayed = room.spectator_match_view().game.expect("match is running");
+        assert_eq!(delayed.flags, 0);
+
+        room.configure(
+            &owner,
+            RoomSettingsUpdate {
+                hide_flags_from_spectators: Some(true),
+                spectator_delay_secs: Some(0),
+                ..RoomSettingsUpdate::default()
+            },
+        )
+        .expect("owner can configure");
+        let hidden = room.spectator_match_view().game.expect("match is running");
+        assert_eq!(
+            (hidden.flags, hidden.mines_remaining),
+            (0, hidden.mines.into())
+        );
+        assert_eq!(room.match_view().game.expect("match is running").flags, 1);
+    }
+
+    #[tokio::test]
+    async fn banned_players_and_accounts_cannot_rejoin() {
+        let (registry, _registry_mailbox) = mpsc::channel(10);
+        let (player_addr, _player_mailbox) = mpsc::channel(10);
+        let (owner, griefer, bystander) = (
+            "P00001".to_string(),
+            "P00002".to_string(),
+            "P00003".to_string(),
+        );
+        let mut room = Room::new("quiet-otter".to_string(), registry);
+        room.register_player(owner.clone(), player_addr.clone(), None);
+        room.register_player(
+            griefer.clone(),
+            player_addr.clone(),
+            Some(account("mallory")),
+        );
+        room.register_player(bystander.clone(), player_addr, None);
+
+        assert!(matches!(
+            room.kick(&griefer, &owner, false).await,
+            Err(RoomError::NotOwner)
+        ));
+        assert!(matches!(
+            room.kick(&owner, &owner, false).await,
+            Err(RoomError::CannotTargetSelf)
+        ));
+        room.kick(&owner, &griefer, true)
+            .await
+            .expect("owner can ban");
+        assert!(!room.players.contains_key(&griefer));
+        assert!(room.is_banned(&griefer, None));
+        assert!(room.is_banned(&"P00009".to_string(), Some(&account("mallory"))));
+
+        room.transfer_owner(&owner, &bystander)
+            .expect("owner can hand over the room");
+        room.drop_player(&bystander)
+            .await
+            .expect("bystander leaves");
+        assert_eq!(room.owner.as_deref(), Some("P00001"));
+    }
+
+    #[test]
+    fn matches_start_once_enough_players_are_ready() {
+        let (registry, _registry_mailbox) = mpsc::channel(10);
+        let (player_addr, _player_mailbox) = mpsc::channel(10);
+        let (owner, guest) = ("P00001".to_string(), "P00002".to_string());
+        let mut room = Room::new("quiet-otter".to_string(), registry);
+        room.register_player(owner.clone(), player_addr.clone(), None);
+        room.register_player(guest.clone(), player_addr, None);
+        room.configure(
+            &owner,
+            RoomSettingsUpdate {
+                auto_start_secs: Some(5),
+                ..RoomSettingsUpdate::default()
+            },
+        )
+        .expect("owner can configure");
+
+        room.set_ready(&guest, true).expect("guest can ready up");
+        assert!(room.update_countdown().is_none());
+        assert!(matches!(
+            room.start_game(owner.clone(), GameDifficulty::TEST),
+            Err(RoomError::NotEnoughReady {
+                ready: 1,
+                required: 2
+            })
+        ));
+
+        room.set_ready(&owner, true).expect("owner can ready up");
+        assert!(matches!(
+            room.update_countdown(),
+            Some(SessionEvent::CountdownStarted { starts_in_secs: 5 })
+        ));
+        room.set_ready(&owner, false).expect("owner can stand down");
+        assert!(matches!(
+            room.update_countdown(),
+            Some(SessionEvent::CountdownCancelled)
+        ));
+
+        room.configure(
+            &owner,
+            RoomSettingsUpdate {
+                min_ready: Some(1),
+                ..RoomSettingsUpdate::default()
+            },
+        )
+        .expect("owner can configure");
+        room.start_game(owner.clone(), GameDifficulty::TEST)
+            .expect("one ready player is enough");
+        let RoomMatchState::Playing(active_match) = &room.match_state else {
+            panic!("match should have started");
+        };
+        assert_eq!(active_match.participants, vec![guest.clone()]);
+        assert_eq!(active_match.current_player, guest);
+        assert_eq!(room.players[&owner].state, PlayerState::Spectator);
+    }
+
+    #[tokio::test]
+    async fn chat_rate_limits_survive_leaving_and_rejoining() {
+        let (registry, _registry_mailbox) = mpsc::channel(10);
+        let (player_addr, _player_mailbox) = mpsc::channel(10);
+        let id = "P00001".to_string();
+        let mut room = Room::new("quiet-otter".to_string(), registry);
+        room.register_player(id.clone(), player_addr.clone(), None);
+        while room.post_chat(&id, "hi").is_ok() {}
+
+        room.drop_player(&id).await.expect("player can leave");
+        room.register_player(id.clone(), player_addr, None);
+        assert!(matches!(
+            room.post_chat(&id, "hi"),
+            Err(RoomError::Chat(ChatError::RateLimited))
+        ));
+    }
+
+    #[tokio::test]
+    async fn turn_order_follows_the_room_setting() {
+        let (registry, _registry_mailbox) = mpsc::channel(10);
+        let (player_addr, _player_mailbox) = mpsc::channel(10);
+        let ids = ["P00001", "P00002", "P00003"].map(String::from);
+        let mut room = Room::new("quiet-otter".to_string(), registry);
+        for id in &ids {
+            room.register_player(id.clone(), player_addr.clone(), None);
+            room.set_ready(id, true).expect("players can ready up");
+        }
+        room.start_game(ids[0].clone(), GameDifficulty::TEST)
+            .expect("owner can start");
+        let ProtocolMatchState::Playing { turn_queue, .. } = room.match_view().state else {
+            panic!("match should have started");
+        };
+        assert_eq!(turn_queue, ids[1..]);
+
+        room.mark_player_eliminated(&ids[1])
+            .expect("player can be eliminated");
+        room.finish_with_winner().expect("match can finish");
+        room.configure(
+            &ids[0],
+            RoomSettingsUpdate {
+                turn_order: Some(TurnOrder::LoserFirst),
+                ..RoomSettingsUpdate::default()
+            },
+        )
+        .expect("owner can configure");
+        for id in &ids {
+            room.set_ready(id, true).expect("players can ready up");
+        }
+        assert_eq!(
+            room.turn_order(7),
+            vec![ids[1].clone(), ids[0].clone(), ids[2].clone()]
+        );
+
+        room.settings.turn_order = TurnOrder::Random;
+        let shuffled = room.turn_order(7);
+        assert_eq!(shuffled, room.turn_order(7));
+        let mut sorted = shuffled.clone();
+        sorted.sort();
+        assert_eq!(sorted, ids);
+    }
+
+    #[test]
+    fn turn_policies_decide_which_actions_pass_the_turn() {
+        let (registry, _registry_mailbox) = mpsc::channel(10);
+        let (player_addr, _player_mailbox) = mpsc::channel(10);
+        let owner = "P00001".to_string();
+        let mut room = Room::new("quiet-otter".to_string(), registry);
+        room.register_player(owner.clone(), player_addr, None);
+        room.set_ready(&owner, true).expect("owner can ready up");
+        room.start_game(owner, GameDifficulty::TEST)
+            .expect("owner can start");
+        let flag = GameAction::Flag { x: 0, y: 0 };
+        let flagged = match &mut room.match_state {
+            RoomMatchState::Playing(active_match) => active_match
+                .game
+                .handle_action(flag)
+                .expect("flag should apply")
+                .clone(),
+            _ => panic!("match should have started"),
+        };
+
+        assert!(room.turn_ends(flag, &flagged));
+        room.settings.turn_policy = TurnPolicy::FreeFlags;
+        assert!(!room.turn_ends(flag, &flagged));
+
+        let reveal = GameAction::Reveal { x: 0, y: 0 };
+        room.settings.turn_policy = TurnPolicy::Reveals { per_turn: 2 };
+        if let RoomMatchState::Playing(active_match) = &mut room.match_state {
+            active_match.reveals_this_turn = 1;
+        }
+        assert!(!room.turn_ends(reveal, &flagged));
+        if let RoomMatchState::Playing(active_match) = &mut room.match_state {
+            active_match.reveals_this_turn = 2;
+        }
+        assert!(room.turn_ends(reveal, &flagged));
     }
 }
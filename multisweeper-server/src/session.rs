@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::time::Duration;
 
 use anyhow::Result;
 use futures::{
@@ -7,34 +7,75 @@ use futures::{
 };
 use thiserror::Error;
 use tokio::{
-    net::TcpStream,
     sync::{
         mpsc::{self, Receiver, Sender},
         oneshot,
     },
+    time::{Instant, sleep_until},
 };
 use tokio_tungstenite::{
     WebSocketStream,
     tungstenite::{Error, Message},
 };
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 use crate::{
+    accounts::{Account, AccountError, Identity},
+    matchmaking::QueuedPlayer,
+    metrics::METRICS,
     protocol::{
         registry::RegistryMessage,
-        room::{PlayerCommand, RequestContext, RoomMessage},
+        room::{PlayerCommand, RequestContext, RoomMessage, RoomSettingsUpdate},
         session::{ClientError, ErrorCode, MessageId, SessionMessage},
-        wire::{ClientRequest, ServerMessage},
+        wire::{
+            BoardEncoding, ClientRequest, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION, ProtocolFeature,
+            ServerMessage, WireEncoding, WireError,
+        },
     },
-    registry::{RegistryAddr, RegistryError},
+    ratelimit::{RecentSet, TokenBucket},
+    registry::{RegistryAddr, RegistryError, RoomListQuery, RoomPage},
     room::{RoomAddr, RoomCode},
+    shutdown::{self, ShutdownNotice, ShutdownSignal},
+    stats::{LeaderboardEntry, PlayerStats},
+    tls::ClientConnection,
 };
 
 pub type PlayerId = String;
 pub type PlayerMailbox = Receiver<SessionMessage>;
 pub type PlayerAddr = Sender<SessionMessage>;
-pub type PlayerInbound = SplitStream<WebSocketStream<TcpStream>>;
-pub type PlayerOutbound = SplitSink<WebSocketStream<TcpStream>, Message>;
+pub type PlayerInbound = SplitStream<WebSocketStream<ClientConnection>>;
+pub type PlayerOutbound = SplitSink<WebSocketStream<ClientConnection>, Message>;
+
+const DEFAULT_MAILBOX_CAPACITY: usize = 10;
+const DEFAULT_LEADERBOARD_LIMIT: u16 = 10;
+const MAX_LEADERBOARD_LIMIT: u16 = 100;
+/// Requests a client may send in a burst before being throttled.
+const REQUEST_BURST: u32 = 20;
+/// How quickly a throttled client earns back a request.
+const REQUEST_REFILL: Duration = Duration::from_millis(100);
+/// Throttled requests tolerated in a burst before the connection is dropped.
+const STRIKE_BURST: u32 = 50;
+const STRIKE_REFILL: Duration = Duration::from_secs(1);
+/// How many recent message ids are remembered to reject duplicates.
+const SEEN_MESSAGE_IDS: usize = 1024;
+/// Longest `connection.hello` client name written to the logs.
+const MAX_LOGGED_CLIENT_CHARS: usize = 64;
+
+/// How often the server pings a connection and how long it may stay silent before being dropped.
+#[derive(Debug, Clone, Copy)]
+pub struct Heartbeat {
+    pub ping_interval: Duration,
+    pub idle_timeout: Duration,
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Heartbeat {
+            ping_interval: Duration::from_secs(15),
+            idle_timeout: Duration::from_secs(45),
+        }
+    }
+}
 
 #[derive(Debug, Error)]
 pub enum SessionError {
@@ -48,15 +89,29 @@ pub enum SessionError {
     RoomAlreadyJoined,
     #[error("no room joined")]
     NoRoomJoined,
+    #[error("identify with player.identify first")]
+    NotIdentified,
+    #[error("not waiting in a matchmaking queue")]
+    NotQueued,
+    #[error("too many requests, closing the connection")]
+    Flooding,
+    #[error(
+        "protocol version {0} is not supported; this server speaks versions {MIN_PROTOCOL_VERSION} to {PROTOCOL_VERSION}"
+    )]
+    UnsupportedProtocolVersion(u32),
 }
 
 impl SessionError {
     fn client_error(&self) -> ClientError {
         let code = match self {
             Self::ConnectionTerminated => ErrorCode::RoomUnavailable,
+            Self::Flooding => ErrorCode::RateLimited,
             Self::MailboxDropped | Self::RoomDropped => ErrorCode::RoomDropped,
             Self::RoomAlreadyJoined => ErrorCode::RoomAlreadyJoined,
             Self::NoRoomJoined => ErrorCode::NoRoomJoined,
+            Self::NotIdentified => ErrorCode::NotIdentified,
+            Self::NotQueued => ErrorCode::NotQueued,
+            Self::UnsupportedProtocolVersion(_) => ErrorCode::UnsupportedProtocolVersion,
         };
         ClientError::new(code, self.to_string())
     }
@@ -65,13 +120,17 @@ impl SessionError {
 enum InboundError {
     ConnectionTerminated,
     Transport(Error),
-    Malformed(serde_json::Error),
+    Malformed(WireError),
     UnsupportedFrame,
+    /// A ping or pong; it only counts as a sign of life.
+    Heartbeat,
 }
 
 pub enum SessionEvent {
     Inbound(Option<Result<Message, Error>>),
     Mailbox(Option<SessionMessage>),
+    HeartbeatDue,
+    Shutdown(ShutdownNotice),
 }
 
 pub struct Session {
@@ -82,18 +141,35 @@ pub struct Session {
     inbound: PlayerInbound,
     registry_addr: RegistryAddr,
     room: Option<RoomAddr>,
-    seen_message_ids: HashSet<MessageId>,
+    account: Option<Account>,
+    /// Whether a `matchmaking.join` is waiting for a room.
+    queued: bool,
+    seen_message_ids: RecentSet<MessageId>,
+    /// Limits how fast inbound frames are processed.
+    requests: TokenBucket,
+    /// Drained by throttled frames; running dry means the client keeps flooding.
+    strikes: TokenBucket,
+    heartbeat: Heartbeat,
+    encoding: WireEncoding,
+    board_encoding: BoardEncoding,
+    /// Set once the client asked for a protocol version the server cannot speak.
+    incompatible: bool,
+    /// When the client last sent any frame, pongs included.
+    last_seen: Instant,
+    next_ping: Instant,
+    shutdown: Option<ShutdownSignal>,
     message_counter: u64,
 }
 
 impl Session {
     pub fn new(
         id: PlayerId,
-        stream: WebSocketStream<TcpStream>,
+        stream: WebSocketStream<ClientConnection>,
         registry_addr: RegistryAddr,
     ) -> Self {
-        let (sender, receiver) = mpsc::channel(10);
+        let (sender, receiver) = mpsc::channel(DEFAULT_MAILBOX_CAPACITY);
         let (sink, source) = stream.split();
+        let now = Instant::now();
         Session {
             id,
             mailbox: receiver,
@@ -102,11 +178,47 @@ impl Session {
             inbound: source,
             registry_addr,
             room: None,
-            seen_message_ids: HashSet::new(),
+            account: None,
+            queued: false,
+            seen_message_ids: RecentSet::new(SEEN_MESSAGE_IDS),
+            requests: TokenBucket::new(REQUEST_BURST, REQUEST_REFILL, now),
+            strikes: TokenBucket::new(STRIKE_BURST, STRIKE_REFILL, now),
+            heartbeat: Heartbeat::default(),
+            encoding: WireEncoding::default(),
+            board_encoding: BoardEncoding::default(),
+            incompatible: false,
+            last_seen: now,
+            next_ping: now + Heartbeat::default().ping_interval,
+            shutdown: None,
             message_counter: 0,
         }
     }
 
+    pub fn with_heartbeat(mut self, heartbeat: Heartbeat) -> Self {
+        self.heartbeat = heartbeat;
+        self.next_ping = self.last_seen + heartbeat.ping_interval;
+        self
+    }
+
+    /// Replaces the session's mailbox; call this before taking a [`Session::request_addr`].
+    pub fn with_mailbox_capacity(mut self, capacity: usize) -> Self {
+        let (sender, receiver) = mpsc::channel(capacity);
+        self.addr = sender;
+        self.mailbox = receiver;
+        self
+    }
+
+    /// Sets the encoding negotiated during the WebSocket handshake.
+    pub fn with_encoding(mut self, encoding: WireEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn with_shutdown(mut self, shutdown: ShutdownSignal) -> Self {
+        self.shutdown = Some(shutdown);
+        self
+    }
+
     pub fn id(&self) -> &str {
         &self.id
     }
@@ -117,12 +229,26 @@ impl Session {
 
     #[tracing::instrument(name = "session.lifecycle", skip_all, fields(player_id = %self.id))]
     pub async fn handle_connections(mut self) -> Result<()> {
+        METRICS.sessions.inc();
         let message_id = self.next_message_id();
-        self.send_outbound(ServerMessage::ConnectionReady {
-            message_id,
-            player_id: self.id.clone(),
-        })
-        .await?;
+        if let Err(e) = self
+            .send_outbound(ServerMessage::ConnectionReady {
+                message_id,
+                player_id: self.id.clone(),
+                protocol_version: PROTOCOL_VERSION,
+            })
+            .await
+        {
+            self.terminate().await;
+            return Err(e);
+        }
+        let _ = self
+            .registry_addr
+            .send(RegistryMessage::AttachSession {
+                id: self.id.clone(),
+                addr: self.addr.clone(),
+            })
+            .await;
         match self.event_loop().await {
             Ok(()) => (),
             Err(e) => {
@@ -149,12 +275,21 @@ impl Session {
         loop {
             let event = tokio::select! {
                 req = self.inbound.next() => SessionEvent::Inbound(req),
-                msg = self.mailbox.recv() => SessionEvent::Mailbox(msg)
+                msg = self.mailbox.recv() => SessionEvent::Mailbox(msg),
+                _ = sleep_until(self.next_ping) => SessionEvent::HeartbeatDue,
+                notice = shutdown::requested(self.shutdown.as_mut()) => SessionEvent::Shutdown(notice)
             };
 
             match event {
                 SessionEvent::Inbound(client_request) => {
-                    match self.receive_inbound(client_request) {
+                    self.last_seen = Instant::now();
+                    let inbound = self.receive_inbound(client_request);
+                    if matches!(inbound, Ok(_) | Err(InboundError::Malformed(_)))
+                        && !self.throttle(&inbound).await?
+                    {
+                        continue;
+                    }
+                    match inbound {
                         Ok(request) => {
                             let message_id = request.message_id().clone();
                             if let Err(error) = self.accept_message_id(&message_id) {
@@ -162,6 +297,14 @@ impl Session {
                             } else {
                                 self.handle_inbound(request).await?;
                             }
+                            if self.incompatible {
+                                info!(
+                                    target: "multisweeper.session.incompatible",
+                                    player_id = %self.id,
+                                    "closing a client that speaks an unsupported protocol version"
+                                );
+                                return Ok(());
+                            }
                         }
                         Err(InboundError::Malformed(error)) => {
                             self.send_rejection(
@@ -183,6 +326,7 @@ impl Session {
                             )
                             .await?;
                         }
+                        Err(InboundError::Heartbeat) => (),
                         Err(InboundError::ConnectionTerminated) => {
                             return Err(SessionError::ConnectionTerminated.into());
                         }
@@ -191,7 +335,48 @@ impl Session {
                 }
                 SessionEvent::Mailbox(server_message) => {
                     let message = self.receive_mailbox(server_message)?;
+                    let disconnected = matches!(
+                        message,
+                        SessionMessage::Broadcast(
+                            crate::protocol::session::SessionEvent::Disconnected { .. }
+                        )
+                    );
                     self.handle_mailbox(message).await?;
+                    if disconnected {
+                        info!(
+                            target: "multisweeper.session.disconnected",
+                            player_id = %self.id,
+                            "session disconnected by an operator"
+                        );
+                        return Ok(());
+                    }
+                }
+                SessionEvent::HeartbeatDue => {
+                    let now = Instant::now();
+                    if now.saturating_duration_since(self.last_seen) >= self.heartbeat.idle_timeout
+                    {
+                        info!(
+                            target: "multisweeper.session.idle_timeout",
+                            player_id = %self.id,
+                            idle_secs = now.saturating_duration_since(self.last_seen).as_secs(),
+                            "closing an unresponsive session"
+                        );
+                        return Ok(());
+                    }
+                    self.next_ping = now + self.heartbeat.ping_interval;
+                    self.outbound
+                        .send(Message::Ping(Default::default()))
+                        .await?;
+                }
+                SessionEvent::Shutdown(notice) => {
+                    let message_id = self.next_message_id();
+                    self.send_outbound(ServerMessage::ServerShutdown {
+                        message_id,
+                        reason: notice.reason,
+                        restart_in_secs: notice.restart_in_secs,
+                    })
+                    .await?;
+                    return Ok(());
                 }
             }
         }
@@ -202,15 +387,52 @@ impl Session {
         req: Option<Result<Message, Error>>,
     ) -> Result<ClientRequest, InboundError> {
         match req {
-            Some(Ok(message @ (Message::Text(_) | Message::Binary(_)))) => {
-                message.try_into().map_err(InboundError::Malformed)
-            }
+            Some(Ok(message @ (Message::Text(_) | Message::Binary(_)))) => self
+                .encoding
+                .decode(message)
+                .map_err(InboundError::Malformed),
             Some(Ok(Message::Close(_))) | None => Err(InboundError::ConnectionTerminated),
+            Some(Ok(Message::Ping(_) | Message::Pong(_))) => Err(InboundError::Heartbeat),
             Some(Ok(_)) => Err(InboundError::UnsupportedFrame),
             Some(Err(error)) => Err(InboundError::Transport(error)),
         }
     }
 
+    /// Spends a request token on an inbound frame, rejecting it when the client is over its
+    /// limit and disconnecting a client that keeps flooding. Returns whether to process it.
+    async fn throttle(&mut self, inbound: &Result<ClientRequest, InboundError>) -> Result<bool> {
+        let now = Instant::now();
+        if self.requests.try_take(now) {
+            return Ok(true);
+        }
+        let message_id = inbound
+            .as_ref()
+            .ok()
+            .map(|request| request.message_id().clone());
+        if !self.strikes.try_take(now) {
+            warn!(
+                target: "multisweeper.session.flooding",
+                player_id = %self.id,
+                "disconnecting a client that kept exceeding the request rate limit"
+            );
+            let error = SessionError::Flooding;
+            self.send_rejection(message_id, error.client_error())
+                .await?;
+            return Err(error.into());
+        }
+        debug!(
+            target: "multisweeper.session.rate_limited",
+            player_id = %self.id,
+            "client request rate limited"
+        );
+        self.send_rejection(
+            message_id,
+            ClientError::new(ErrorCode::RateLimited, "sending requests too quickly"),
+        )
+        .await?;
+        Ok(false)
+    }
+
     fn accept_message_id(&mut self, message_id: &MessageId) -> Result<(), ClientError> {
         if message_id.is_empty() || message_id.len() > 128 {
             return Err(ClientError::new(
@@ -221,7 +443,7 @@ impl Session {
         if !self.seen_message_ids.insert(message_id.clone()) {
             return Err(ClientError::new(
                 ErrorCode::DuplicateMessageId,
-                "message_id was already used on this connection",
+                "message_id was recently used on this connection",
             ));
         }
         Ok(())
@@ -252,6 +474,75 @@ impl Session {
                     })
                     .await?)
             }
+            ClientRequest::ConnectionHello {
+                message_id,
+                protocol_version,
+                client,
+            } => {
+                let compatible =
+                    (MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&protocol_version);
+                // The name is client-supplied, so only a bounded amount of it reaches the logs.
+                let client: String = client
+                    .as_deref()
+                    .unwrap_or("unknown")
+                    .chars()
+                    .take(MAX_LOGGED_CLIENT_CHARS)
+                    .collect();
+                info!(
+                    target: "multisweeper.session.hello",
+                    player_id = %self.id,
+                    protocol_version,
+                    client = %client,
+                    compatible,
+                    "client introduced itself"
+                );
+                if !compatible {
+                    self.incompatible = true;
+                    return self
+                        .send_rejection(
+                            Some(message_id),
+                            SessionError::UnsupportedProtocolVersion(protocol_version)
+                                .client_error(),
+                        )
+                        .await;
+                }
+                let response_message_id = self.next_message_id();
+                Ok(self
+                    .send_outbound(ServerMessage::ConnectionWelcome {
+                        message_id: response_message_id,
+                        correlation_id: message_id,
+                        protocol_version: PROTOCOL_VERSION,
+                        min_protocol_version: MIN_PROTOCOL_VERSION,
+                        features: ProtocolFeature::ALL.to_vec(),
+                    })
+                    .await?)
+            }
+            ClientRequest::ConnectionEncoding {
+                message_id,
+                encoding,
+                board,
+            } => {
+                self.encoding = encoding;
+                if let Some(board) = board {
+                    self.board_encoding = board;
+                }
+                info!(
+                    target: "multisweeper.session.encoding_changed",
+                    player_id = %self.id,
+                    encoding = ?encoding,
+                    board_encoding = ?self.board_encoding,
+                    "wire encoding changed"
+                );
+                let response_message_id = self.next_message_id();
+                Ok(self
+                    .send_outbound(ServerMessage::ConnectionEncodingSet {
+                        message_id: response_message_id,
+                        correlation_id: message_id,
+                        encoding,
+                        board: self.board_encoding,
+                    })
+                    .await?)
+            }
             ClientRequest::RoomCreate { message_id } => {
                 if self.room.is_some() {
                     return self
@@ -261,7 +552,7 @@ impl Session {
                         )
                         .await;
                 }
-                let (reply_sdr, reply_rcr) = oneshot::channel::<RoomAddr>();
+                let (reply_sdr, reply_rcr) = oneshot::channel::<Result<RoomAddr, RegistryError>>();
                 if self
                     .registry_addr
                     .send(RegistryMessage::CreateLobby(reply_sdr))
@@ -273,7 +564,12 @@ impl Session {
                         .await;
                 }
                 let addr = match reply_rcr.await {
-                    Ok(addr) => addr,
+                    Ok(Ok(addr)) => addr,
+                    Ok(Err(error)) => {
+                        return self
+                            .send_rejection(Some(message_id), registry_client_error(error))
+                            .await;
+                    }
                     Err(_) => {
                         return self
                             .send_rejection(
@@ -283,9 +579,17 @@ impl Session {
                             .await;
                     }
                 };
+                self.leave_matchmaking().await;
                 self.room = Some(addr);
-                self.send_room_or_reject(message_id, PlayerCommand::Join)
-                    .await
+                let account = self.account.clone();
+                self.send_room_or_reject(
+                    message_id,
+                    PlayerCommand::Join {
+                        account,
+                        ready: false,
+                    },
+                )
+                .await
             }
             ClientRequest::RoomJoin {
                 message_id,
@@ -299,11 +603,33 @@ impl Session {
                         )
                         .await;
                 }
-                let (reply_sdr, reply_rcr) = oneshot::channel::<Result<RoomAddr, RegistryError>>();
+                self.leave_matchmaking().await;
+                self.join_room(message_id, room_code, false).await
+            }
+            ClientRequest::MatchmakingJoin {
+                message_id,
+                difficulty,
+                mode,
+            } => {
+                if self.room.is_some() {
+                    return self
+                        .send_rejection(
+                            Some(message_id),
+                            SessionError::RoomAlreadyJoined.client_error(),
+                        )
+                        .await;
+                }
+                let (reply_sdr, reply_rcr) = oneshot::channel::<usize>();
+                let player = QueuedPlayer {
+                    id: self.id.clone(),
+                    addr: self.addr.clone(),
+                    request_id: message_id.clone(),
+                };
                 if self
                     .registry_addr
-                    .send(RegistryMessage::RequestLobby {
-                        code: room_code,
+                    .send(RegistryMessage::JoinMatchmaking {
+                        key: (mode, difficulty),
+                        player,
                         reply: reply_sdr,
                     })
                     .await
@@ -313,8 +639,8 @@ impl Session {
                         .send_rejection(Some(message_id), SessionError::RoomDropped.client_error())
                         .await;
                 }
-                let maybe_lobby_handle = match reply_rcr.await {
-                    Ok(result) => result,
+                let queued = match reply_rcr.await {
+                    Ok(queued) => queued,
                     Err(_) => {
                         return self
                             .send_rejection(
@@ -324,26 +650,31 @@ impl Session {
                             .await;
                     }
                 };
-                match maybe_lobby_handle {
-                    Ok(addr) => {
-                        self.room = Some(addr);
-                        self.send_room_or_reject(message_id, PlayerCommand::Join)
-                            .await?
-                    }
-                    Err(e) => {
-                        let error = match e {
-                            RegistryError::RoomNotFound(code) => ClientError::new(
-                                ErrorCode::RoomNotFound,
-                                format!("requested room with code {code} not found"),
-                            ),
-                            RegistryError::AddrDropped | RegistryError::ReplyFailed => {
-                                SessionError::RoomDropped.client_error()
-                            }
-                        };
-                        self.send_rejection(Some(message_id), error).await?
-                    }
+                self.queued = true;
+                let response_message_id = self.next_message_id();
+                Ok(self
+                    .send_outbound(ServerMessage::MatchmakingQueued {
+                        message_id: response_message_id,
+                        correlation_id: message_id,
+                        mode,
+                        difficulty,
+                        queued,
+                    })
+                    .await?)
+            }
+            ClientRequest::MatchmakingLeave { message_id } => {
+                if !self.leave_matchmaking().await {
+                    return self
+                        .send_rejection(Some(message_id), SessionError::NotQueued.client_error())
+                        .await;
                 }
-                Ok(())
+                let response_message_id = self.next_message_id();
+                Ok(self
+                    .send_outbound(ServerMessage::MatchmakingLeft {
+                        message_id: response_message_id,
+                        correlation_id: message_id,
+                    })
+                    .await?)
             }
             ClientRequest::RoomLeave { message_id } => {
                 if self.room.is_none() {
@@ -354,11 +685,30 @@ impl Session {
                 self.send_room_or_reject(message_id, PlayerCommand::Leave)
                     .await
             }
-            ClientRequest::RoomsList { message_id } => {
-                let (reply_sdr, reply_rcr) = oneshot::channel::<Vec<RoomCode>>();
+            ClientRequest::RoomsList {
+                message_id,
+                difficulty,
+                mode,
+                match_state,
+                open_only,
+                offset,
+                limit,
+            } => {
+                let query = RoomListQuery {
+                    difficulty,
+                    mode,
+                    match_state,
+                    open_only,
+                    offset,
+                    limit,
+                };
+                let (reply_sdr, reply_rcr) = oneshot::channel::<RoomPage>();
                 if self
                     .registry_addr
-                    .send(RegistryMessage::QueryLobbies(reply_sdr))
+                    .send(RegistryMessage::QueryLobbies {
+                        query,
+                        reply: reply_sdr,
+                    })
                     .await
                     .is_err()
                 {
@@ -366,8 +716,8 @@ impl Session {
                         .send_rejection(Some(message_id), SessionError::RoomDropped.client_error())
                         .await;
                 }
-                let rooms = match reply_rcr.await {
-                    Ok(rooms) => rooms,
+                let page = match reply_rcr.await {
+                    Ok(page) => page,
                     Err(_) => {
                         return self
                             .send_rejection(
@@ -380,12 +730,55 @@ impl Session {
                 let response_message_id = self.next_message_id();
                 Ok(self
                     .send_outbound(ServerMessage::RoomsListed {
+                        message_id: response_message_id,
+                        correlation_id: message_id,
+                        rooms: page.rooms,
+                        total: page.total,
+                    })
+                    .await?)
+            }
+            ClientRequest::RoomsSubscribe { message_id } => {
+                let (reply_sdr, reply_rcr) = oneshot::channel();
+                if self
+                    .registry_addr
+                    .send(RegistryMessage::SubscribeRooms {
+                        id: self.id.clone(),
+                        reply: reply_sdr,
+                    })
+                    .await
+                    .is_err()
+                {
+                    return self
+                        .send_rejection(Some(message_id), SessionError::RoomDropped.client_error())
+                        .await;
+                }
+                let Ok(rooms) = reply_rcr.await else {
+                    return self
+                        .send_rejection(Some(message_id), SessionError::RoomDropped.client_error())
+                        .await;
+                };
+                let response_message_id = self.next_message_id();
+                Ok(self
+                    .send_outbound(ServerMessage::RoomsSubscribed {
                         message_id: response_message_id,
                         correlation_id: message_id,
                         rooms,
                     })
                     .await?)
             }
+            ClientRequest::RoomsUnsubscribe { message_id } => {
+                let _ = self
+                    .registry_addr
+                    .send(RegistryMessage::UnsubscribeRooms(self.id.clone()))
+                    .await;
+                let response_message_id = self.next_message_id();
+                Ok(self
+                    .send_outbound(ServerMessage::RoomsUnsubscribed {
+                        message_id: response_message_id,
+                        correlation_id: message_id,
+                    })
+                    .await?)
+            }
             ClientRequest::GameStart {
                 message_id,
                 difficulty,
@@ -431,6 +824,239 @@ impl Session {
                 self.send_room_or_reject(message_id, PlayerCommand::GameQuery)
                     .await
             }
+            ClientRequest::PlayerIdentify {
+                message_id,
+                display_name,
+                token,
+            } => {
+                let (reply_sdr, reply_rcr) = oneshot::channel::<Result<Identity, RegistryError>>();
+                if self
+                    .registry_addr
+                    .send(RegistryMessage::IdentifyPlayer {
+                        id: self.id.clone(),
+                        token,
+                        display_name,
+                        reply: reply_sdr,
+                    })
+                    .await
+                    .is_err()
+                {
+                    return self
+                        .send_rejection(Some(message_id), SessionError::RoomDropped.client_error())
+                        .await;
+                }
+                let identity = match reply_rcr.await {
+                    Ok(Ok(identity)) => identity,
+                    Ok(Err(e)) => {
+                        return self
+                            .send_rejection(Some(message_id), registry_client_error(e))
+                            .await;
+                    }
+                    Err(_) => {
+                        return self
+                            .send_rejection(
+                                Some(message_id),
+                                SessionError::RoomDropped.client_error(),
+                            )
+                            .await;
+                    }
+                };
+                self.account = Some(identity.account.clone());
+                let response_message_id = self.next_message_id();
+                self.send_outbound(ServerMessage::PlayerIdentified {
+                    message_id: response_message_id,
+                    correlation_id: message_id.clone(),
+                    player_id: self.id.clone(),
+                    account_id: identity.account.id.clone(),
+                    display_name: identity.account.display_name.clone(),
+                    token: identity.token,
+                })
+                .await?;
+                if self.room.is_some() {
+                    return self
+                        .send_room_or_reject(
+                            message_id,
+                            PlayerCommand::Identify {
+                                account: identity.account,
+                            },
+                        )
+                        .await;
+                }
+                Ok(())
+            }
+            ClientRequest::StatsGet {
+                message_id,
+                account_id,
+            } => {
+                let Some(account_id) =
+                    account_id.or_else(|| self.account.as_ref().map(|account| account.id.clone()))
+                else {
+                    return self
+                        .send_rejection(
+                            Some(message_id),
+                            SessionError::NotIdentified.client_error(),
+                        )
+                        .await;
+                };
+                let (reply_sdr, reply_rcr) = oneshot::channel::<PlayerStats>();
+                if self
+                    .registry_addr
+                    .send(RegistryMessage::QueryStats {
+                        account_id,
+                        reply: reply_sdr,
+                    })
+                    .await
+                    .is_err()
+                {
+                    return self
+                        .send_rejection(Some(message_id), SessionError::RoomDropped.client_error())
+                        .await;
+                }
+                let stats = match reply_rcr.await {
+                    Ok(stats) => stats,
+                    Err(_) => {
+                        return self
+                            .send_rejection(
+                                Some(message_id),
+                                SessionError::RoomDropped.client_error(),
+                            )
+                            .await;
+                    }
+                };
+                let response_message_id = self.next_message_id();
+                Ok(self
+                    .send_outbound(ServerMessage::StatsReported {
+                        message_id: response_message_id,
+                        correlation_id: message_id,
+                        stats,
+                    })
+                    .await?)
+            }
+            ClientRequest::LeaderboardGet {
+                message_id,
+                difficulty,
+                limit,
+            } => {
+                let limit = limit
+                    .unwrap_or(DEFAULT_LEADERBOARD_LIMIT)
+                    .min(MAX_LEADERBOARD_LIMIT);
+                let (reply_sdr, reply_rcr) = oneshot::channel::<Vec<LeaderboardEntry>>();
+                if self
+                    .registry_addr
+                    .send(RegistryMessage::QueryLeaderboard {
+                        difficulty,
+                        limit: limit.into(),
+                        reply: reply_sdr,
+                    })
+                    .await
+                    .is_err()
+                {
+                    return self
+                        .send_rejection(Some(message_id), SessionError::RoomDropped.client_error())
+                        .await;
+                }
+                let entries = match reply_rcr.await {
+                    Ok(entries) => entries,
+                    Err(_) => {
+                        return self
+                            .send_rejection(
+                                Some(message_id),
+                                SessionError::RoomDropped.client_error(),
+                            )
+                            .await;
+                    }
+                };
+                let response_message_id = self.next_message_id();
+                Ok(self
+                    .send_outbound(ServerMessage::LeaderboardReported {
+                        message_id: response_message_id,
+                        correlation_id: message_id,
+                        difficulty,
+                        entries,
+                    })
+                    .await?)
+            }
+            ClientRequest::ChatSend { message_id, text } => {
+                if self.room.is_none() {
+                    return self
+                        .send_rejection(Some(message_id), SessionError::NoRoomJoined.client_error())
+                        .await;
+                }
+                self.send_room_or_reject(message_id, PlayerCommand::Chat { text })
+                    .await
+            }
+            ClientRequest::RoomConfigure {
+                message_id,
+                hide_flags_from_spectators,
+                spectator_delay_secs,
+                min_ready,
+                auto_start_secs,
+                auto_start_difficulty,
+                turn_order,
+                turn_policy,
+                private,
+            } => {
+                if self.room.is_none() {
+                    return self
+                        .send_rejection(Some(message_id), SessionError::NoRoomJoined.client_error())
+                        .await;
+                }
+                let changes = RoomSettingsUpdate {
+                    hide_flags_from_spectators,
+                    spectator_delay_secs,
+                    min_ready,
+                    auto_start_secs,
+                    auto_start_difficulty,
+                    turn_order,
+                    turn_policy,
+                    private,
+                };
+                self.send_room_or_reject(message_id, PlayerCommand::Configure { changes })
+                    .await
+            }
+            ClientRequest::RoomReady { message_id, ready } => {
+                if self.room.is_none() {
+                    return self
+                        .send_rejection(Some(message_id), SessionError::NoRoomJoined.client_error())
+                        .await;
+                }
+                self.send_room_or_reject(message_id, PlayerCommand::Ready { ready })
+                    .await
+            }
+            ClientRequest::RoomKick {
+                message_id,
+                player_id,
+                ban,
+            } => {
+                if self.room.is_none() {
+                    return self
+                        .send_rejection(Some(message_id), SessionError::NoRoomJoined.client_error())
+                        .await;
+                }
+                self.send_room_or_reject(
+                    message_id,
+                    PlayerCommand::Kick {
+                        target: player_id,
+                        ban,
+                    },
+                )
+                .await
+            }
+            ClientRequest::RoomTransferOwner {
+                message_id,
+                player_id,
+            } => {
+                if self.room.is_none() {
+                    return self
+                        .send_rejection(Some(message_id), SessionError::NoRoomJoined.client_error())
+                        .await;
+                }
+                self.send_room_or_reject(
+                    message_id,
+                    PlayerCommand::TransferOwner { target: player_id },
+                )
+                .await
+            }
         }
     }
 
@@ -442,6 +1068,17 @@ impl Session {
             message = session_message_name(&message),
             "server message received"
         );
+        // The lobby browser is only live while the player is not in a room.
+        if self.room.is_some()
+            && matches!(
+                &message,
+                SessionMessage::Broadcast(
+                    crate::protocol::session::SessionEvent::RoomsUpdated { .. }
+                )
+            )
+        {
+            return Ok(());
+        }
         if matches!(
             &message,
             SessionMessage::Reply {
@@ -454,13 +1091,90 @@ impl Session {
         ) {
             self.room = None;
         }
+        let match_found = match &message {
+            SessionMessage::Reply {
+                request_id,
+                message: crate::protocol::session::SessionEvent::MatchFound { room_code },
+            } => Some((request_id.clone(), room_code.clone())),
+            _ => None,
+        };
         let response_message_id = self.next_message_id();
         self.send_outbound(ServerMessage::from_session(response_message_id, message))
             .await?;
 
+        if let Some((request_id, room_code)) = match_found {
+            self.queued = false;
+            if self.room.is_none() {
+                self.join_room(request_id, room_code, true).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn join_room(
+        &mut self,
+        message_id: MessageId,
+        room_code: RoomCode,
+        ready: bool,
+    ) -> Result<()> {
+        let (reply_sdr, reply_rcr) = oneshot::channel::<Result<RoomAddr, RegistryError>>();
+        if self
+            .registry_addr
+            .send(RegistryMessage::RequestLobby {
+                code: room_code,
+                reply: reply_sdr,
+            })
+            .await
+            .is_err()
+        {
+            return self
+                .send_rejection(Some(message_id), SessionError::RoomDropped.client_error())
+                .await;
+        }
+        let maybe_lobby_handle = match reply_rcr.await {
+            Ok(result) => result,
+            Err(_) => {
+                return self
+                    .send_rejection(Some(message_id), SessionError::RoomDropped.client_error())
+                    .await;
+            }
+        };
+        match maybe_lobby_handle {
+            Ok(addr) => {
+                self.room = Some(addr);
+                let account = self.account.clone();
+                self.send_room_or_reject(message_id, PlayerCommand::Join { account, ready })
+                    .await?
+            }
+            Err(e) => {
+                self.send_rejection(Some(message_id), registry_client_error(e))
+                    .await?
+            }
+        }
         Ok(())
     }
 
+    /// Takes this session out of the matchmaking queue, returning whether it was queued.
+    async fn leave_matchmaking(&mut self) -> bool {
+        if !std::mem::take(&mut self.queued) {
+            return false;
+        }
+        let (reply_sdr, reply_rcr) = oneshot::channel::<bool>();
+        if self
+            .registry_addr
+            .send(RegistryMessage::LeaveMatchmaking {
+                id: self.id.clone(),
+                reply: reply_sdr,
+            })
+            .await
+            .is_err()
+        {
+            return false;
+        }
+        reply_rcr.await.unwrap_or(false)
+    }
+
     async fn send_rejection(
         &mut self,
         correlation_id: Option<MessageId>,
@@ -476,14 +1190,18 @@ impl Session {
     }
 
     #[tracing::instrument(name = "session.send_response", skip_all, fields(player_id = %self.id))]
-    async fn send_outbound(&mut self, response: ServerMessage) -> Result<()> {
+    async fn send_outbound(&mut self, mut response: ServerMessage) -> Result<()> {
         debug!(
             target: "multisweeper.session.response_sent",
             player_id = %self.id,
             response = server_message_name(&response),
             "server response sent"
         );
-        self.outbound.send(response.try_into()?).await?;
+        if let ServerMessage::CommandRejected { error, .. } = &response {
+            METRICS.command_rejected(&error.code);
+        }
+        self.board_encoding.apply(&mut response);
+        self.outbound.send(self.encoding.encode(&response)?).await?;
         Ok(())
     }
 
@@ -509,6 +1227,7 @@ impl Session {
                         reply_to: self.addr.clone(),
                     },
                     command,
+                    sent_at: Instant::now(),
                 })
                 .await?),
             None => Err(SessionError::RoomDropped.into()),
@@ -531,6 +1250,12 @@ impl Session {
     }
 
     async fn terminate(mut self) {
+        METRICS.sessions.dec();
+        self.leave_matchmaking().await;
+        let _ = self
+            .registry_addr
+            .send(RegistryMessage::ReleasePlayer(self.id.clone()))
+            .await;
         if self.room.is_some() {
             let message_id = self.next_message_id();
             let _ = self.send_room(message_id, PlayerCommand::Leave).await;
@@ -539,26 +1264,71 @@ impl Session {
     }
 }
 
+fn registry_client_error(error: RegistryError) -> ClientError {
+    match error {
+        RegistryError::RoomNotFound(code) => ClientError::new(
+            ErrorCode::RoomNotFound,
+            format!("requested room with code {code} not found"),
+        ),
+        RegistryError::Account(error @ AccountError::NotFound) => {
+            ClientError::new(ErrorCode::AccountNotFound, error.to_string())
+        }
+        RegistryError::Account(error @ AccountError::InvalidDisplayName(_)) => {
+            ClientError::new(ErrorCode::InvalidDisplayName, error.to_string())
+        }
+        RegistryError::RoomLimitReached => {
+            ClientError::new(ErrorCode::ServerFull, error.to_string())
+        }
+        RegistryError::Account(AccountError::Io(_) | AccountError::Corrupt(_)) => ClientError::new(
+            ErrorCode::AccountUnavailable,
+            "accounts are unavailable right now, try again later",
+        ),
+        RegistryError::AddrDropped | RegistryError::ReplyFailed => {
+            SessionError::RoomDropped.client_error()
+        }
+    }
+}
+
 fn client_request_name(request: &ClientRequest) -> &'static str {
     match request {
+        ClientRequest::ConnectionHello { .. } => "connection_hello",
         ClientRequest::ConnectionPing { .. } => "connection_ping",
+        ClientRequest::ConnectionEncoding { .. } => "connection_encoding",
         ClientRequest::RoomsList { .. } => "rooms_list",
+        ClientRequest::RoomsSubscribe { .. } => "rooms_subscribe",
+        ClientRequest::RoomsUnsubscribe { .. } => "rooms_unsubscribe",
         ClientRequest::RoomJoin { .. } => "room_join",
         ClientRequest::RoomCreate { .. } => "room_create",
         ClientRequest::RoomLeave { .. } => "room_leave",
         ClientRequest::GameStart { .. } => "game_start",
         ClientRequest::GameAction { .. } => "game_action",
         ClientRequest::RoomStateGet { .. } => "room_state_get",
+        ClientRequest::PlayerIdentify { .. } => "player_identify",
+        ClientRequest::StatsGet { .. } => "stats_get",
+        ClientRequest::LeaderboardGet { .. } => "leaderboard_get",
+        ClientRequest::ChatSend { .. } => "chat_send",
+        ClientRequest::RoomConfigure { .. } => "room_configure",
+        ClientRequest::RoomKick { .. } => "room_kick",
+        ClientRequest::RoomReady { .. } => "room_ready",
+        ClientRequest::MatchmakingJoin { .. } => "matchmaking_join",
+        ClientRequest::MatchmakingLeave { .. } => "matchmaking_leave",
+        ClientRequest::RoomTransferOwner { .. } => "room_transfer_owner",
     }
 }
 
 fn player_command_name(command: &PlayerCommand) -> &'static str {
     match command {
-        PlayerCommand::Join => "join",
+        PlayerCommand::Join { .. } => "join",
+        PlayerCommand::Identify { .. } => "identify",
         PlayerCommand::Leave => "leave",
         PlayerCommand::StartGame { .. } => "start_game",
         PlayerCommand::GameAction { .. } => "game_action",
         PlayerCommand::GameQuery => "game_query",
+        PlayerCommand::Chat { .. } => "chat",
+        PlayerCommand::Configure { .. } => "configure",
+        PlayerCommand::Kick { .. } => "kick",
+        PlayerCommand::TransferOwner { .. } => "transfer_owner",
+        PlayerCommand::Ready { .. } => "ready",
     }
 }
 
@@ -571,6 +1341,14 @@ fn session_message_name(message: &SessionMessage) -> &'static str {
             crate::protocol::session::SessionEvent::RoomJoinRejected { .. } => "room_join_rejected",
             crate::protocol::session::SessionEvent::Error { .. } => "error",
             crate::protocol::session::SessionEvent::GameStarted => "game_started",
+            crate::protocol::session::SessionEvent::CountdownStarted { .. } => "countdown_started",
+            crate::protocol::session::SessionEvent::CountdownCancelled => "countdown_cancelled",
+            crate::protocol::session::SessionEvent::MatchFound { .. } => "match_found",
+            crate::protocol::session::SessionEvent::ChatMessage(_) => "chat_message",
+            crate::protocol::session::SessionEvent::ChatHistory { .. } => "chat_history",
+            crate::protocol::session::SessionEvent::Announcement { .. } => "announcement",
+            crate::protocol::session::SessionEvent::RoomsUpdated { .. } => "rooms_updated",
+            crate::protocol::session::SessionEvent::Disconnected { .. } => "disconnected",
         },
     }
 }
@@ -578,11 +1356,29 @@ fn session_message_name(message: &SessionMessage) -> &'static str {
 fn server_message_name(response: &ServerMessage) -> &'static str {
     match response {
         ServerMessage::ConnectionReady { .. } => "connection_ready",
+        ServerMessage::ConnectionWelcome { .. } => "connection_welcome",
         ServerMessage::ConnectionPong { .. } => "connection_pong",
+        ServerMessage::ConnectionEncodingSet { .. } => "connection_encoding_set",
         ServerMessage::RoomsListed { .. } => "rooms_listed",
+        ServerMessage::RoomsSubscribed { .. } => "rooms_subscribed",
+        ServerMessage::RoomsUnsubscribed { .. } => "rooms_unsubscribed",
+        ServerMessage::RoomsUpdated { .. } => "rooms_updated",
         ServerMessage::RoomState { .. } => "room_state",
         ServerMessage::RoomRemoved { .. } => "room_removed",
         ServerMessage::CommandRejected { .. } => "command_rejected",
         ServerMessage::GameStarted { .. } => "game_started",
+        ServerMessage::PlayerIdentified { .. } => "player_identified",
+        ServerMessage::StatsReported { .. } => "stats_reported",
+        ServerMessage::LeaderboardReported { .. } => "leaderboard_reported",
+        ServerMessage::MatchmakingQueued { .. } => "matchmaking_queued",
+        ServerMessage::MatchmakingLeft { .. } => "matchmaking_left",
+        ServerMessage::MatchmakingMatched { .. } => "matchmaking_matched",
+        ServerMessage::CountdownStarted { .. } => "countdown_started",
+        ServerMessage::CountdownCancelled { .. } => "countdown_cancelled",
+        ServerMessage::ServerShutdown { .. } => "server_shutdown",
+        ServerMessage::ServerAnnouncement { .. } => "server_announcement",
+        ServerMessage::ConnectionClosed { .. } => "connection_closed",
+        ServerMessage::ChatMessage { .. } => "chat_message",
+        ServerMessage::ChatHistory { .. } => "chat_history",
     }
 }
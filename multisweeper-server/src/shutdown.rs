@@ -0,0 +1,30 @@
+use std::future::pending;
+
+use tokio::sync::watch;
+
+/// Why the server is going away, and roughly when it expects to be back.
+#[derive(Debug, Clone)]
+pub struct ShutdownNotice {
+    pub reason: String,
+    pub restart_in_secs: Option<u32>,
+}
+
+/// Set to `Some` once a shutdown begins; tasks hold a receiver until they have wound down.
+pub type ShutdownSender = watch::Sender<Option<ShutdownNotice>>;
+pub type ShutdownSignal = watch::Receiver<Option<ShutdownNotice>>;
+
+/// Resolves with the notice once a shutdown begins, or never if there is no signal to watch.
+pub async fn requested(signal: Option<&mut ShutdownSignal>) -> ShutdownNotice {
+    let Some(signal) = signal else {
+        return pending().await;
+    };
+    let notice = signal
+        .wait_for(Option::is_some)
+        .await
+        .ok()
+        .and_then(|notice| notice.clone());
+    match notice {
+        Some(notice) => notice,
+        None => pending().await,
+    }
+}
@@ -0,0 +1,459 @@
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use asyncapi_rust::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::warn;
+
+use crate::{
+    accounts::AccountId, protocol::wire::ClientDifficulty, room::RoomCode, session::PlayerId,
+};
+
+#[derive(Debug, Error)]
+pub enum StatsError {
+    #[error("stats store io failed: {0}")]
+    Io(#[from] io::Error),
+    #[error("stats store is corrupt: {0}")]
+    Corrupt(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMode {
+    #[default]
+    TurnBased,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchOutcome {
+    Won,
+    NoWinner,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchParticipant {
+    pub player_id: PlayerId,
+    pub account_id: Option<AccountId>,
+    pub display_name: Option<String>,
+    /// Whether this player was still in the match when the board was cleared.
+    pub won: bool,
+    /// Safe cells revealed by this player.
+    pub score: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchRecord {
+    pub room_code: RoomCode,
+    pub mode: MatchMode,
+    pub difficulty: ClientDifficulty,
+    pub width: u8,
+    pub height: u8,
+    pub mines: u8,
+    pub seed: u64,
+    pub duration_ms: u64,
+    pub outcome: MatchOutcome,
+    /// Unix time in milliseconds.
+    pub finished_at: u64,
+    pub participants: Vec<MatchParticipant>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq)]
+pub struct DifficultyStats {
+    pub difficulty: ClientDifficulty,
+    pub played: u32,
+    pub won: u32,
+    pub win_rate: f64,
+    pub best_time_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq)]
+pub struct PlayerStats {
+    pub account_id: AccountId,
+    pub played: u32,
+    pub won: u32,
+    pub win_rate: f64,
+    pub total_score: u64,
+    pub difficulties: Vec<DifficultyStats>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq)]
+pub struct LeaderboardEntry {
+    pub rank: u32,
+    pub account_id: AccountId,
+    pub display_name: Option<String>,
+    pub best_time_ms: u64,
+    pub played: u32,
+    pub won: u32,
+    pub win_rate: f64,
+}
+
+const DIFFICULTIES: [ClientDifficulty; 4] = [
+    ClientDifficulty::Test,
+    ClientDifficulty::Easy,
+    ClientDifficulty::Medium,
+    ClientDifficulty::Hard,
+];
+
+/// Finished matches, optionally appended to a JSON-lines file so they survive restarts.
+pub struct StatsStore {
+    path: Option<PathBuf>,
+    records: Vec<MatchRecord>,
+    /// Set while the file ends mid-line, so the next append starts on a fresh one.
+    unterminated: bool,
+}
+
+#[derive(Default)]
+struct Tally {
+    played: u32,
+    won: u32,
+    score: u64,
+    best_time_ms: Option<u64>,
+    display_name: Option<String>,
+}
+
+impl Tally {
+    fn add(&mut self, record: &MatchRecord, participant: &MatchParticipant) {
+        self.played += 1;
+        self.score += u64::from(participant.score);
+        if participant.won {
+            self.won += 1;
+            self.best_time_ms = Some(
+                self.best_time_ms
+                    .map_or(record.duration_ms, |best| best.min(record.duration_ms)),
+            );
+        }
+        if participant.display_name.is_some() {
+            self.display_name = participant.display_name.clone();
+        }
+    }
+
+    fn win_rate(&self) -> f64 {
+        match self.played {
+            0 => 0.0,
+            played => f64::from(self.won) / f64::from(played),
+        }
+    }
+}
+
+impl StatsStore {
+    pub fn in_memory() -> Self {
+        StatsStore {
+            path: None,
+            records: Vec::new(),
+            unterminated: false,
+        }
+    }
+
+    /// Loads the store at `path`, starting empty if the file does not exist yet. Lines that do
+    /// not parse, such as one torn by a crash mid-append, are skipped but left in the file.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, StatsError> {
+        let path = path.into();
+        let contents = match fs::read(&path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(error) => return Err(error.into()),
+        };
+        let mut records = Vec::new();
+        for line in String::from_utf8_lossy(&contents).lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(line) {
+                Ok(record) => records.push(record),
+                Err(error) => warn!(
+                    target: "multisweeper.stats.record_skipped",
+                    path = %path.display(),
+                    error = %error,
+                    "unreadable match record skipped"
+                ),
+            }
+        }
+        Ok(StatsStore {
+            path: Some(path),
+            records,
+            unterminated: contents.last().is_some_and(|byte| *byte != b'\n'),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Keeps the record and appends it to the store's file, if it has one.
+    pub fn record(&mut self, record: MatchRecord) -> Result<(), StatsError> {
+        if let Some(pending) = self.push(record)? {
+            pending.write()?;
+            self.mark_appended();
+        }
+        Ok(())
+    }
+
+    /// Keeps the record and returns the line to append to the store's file, if it has one, so
+    /// it can be written away from the caller's task. Call [`StatsStore::mark_appended`] once
+    /// it is written.
+    pub fn push(&mut self, record: MatchRecord) -> Result<Option<PendingAppend>, StatsError> {
+        let pending = match &self.path {
+            Some(path) => {
+                let mut line = Vec::new();
+                if self.unterminated {
+                    line.push(b'\n');
+                }
+                serde_json::to_writer(&mut line, &record).map(|()| {
+                    line.push(b'\n');
+                    Some(PendingAppend {
+                        path: path.clone(),
+                        line,
+                    })
+                })
+            }
+            None => Ok(None),
+        };
+        self.records.push(record);
+        Ok(pending?)
+    }
+
+    pub fn mark_appended(&mut self) {
+        self.unterminated = false;
+    }
+
+    fn tallies_for(&self, account_id: &AccountId) -> HashMap<ClientDifficulty, Tally> {
+        let mut tallies: HashMap<ClientDifficulty, Tally> = HashMap::new();
+        for record in &self.records {
+            for participant in &record.participants {
+                if participant.account_id.as_ref() == Some(account_id) {
+                    tallies
+                        .entry(record.difficulty)
+                        .or_default()
+                        .add(record, participant);
+                }
+            }
+        }
+        tallies
+    }
+
+    pub fn player_stats(&self, account_id: &AccountId) -> PlayerStats {
+        let tallies = self.tallies_for(account_id);
+        let mut overall = Tally::default();
+        let difficulties = DIFFICULTIES
+            .iter()
+            .filter_map(|difficulty| {
+                let tally = tallies.get(difficulty)?;
+                overall.played += tally.played;
+                overall.won += tally.won;
+                overall.score += tally.score;
+                Some(DifficultyStats {
+                    difficulty: *difficulty,
+                    played: tally.played,
+                    won: tally.won,
+                    win_rate: tally.win_rate(),
+                    best_time_ms: tally.best_time_ms,
+                })
+            })
+            .collect();
+        PlayerStats {
+            account_id: account_id.clone(),
+            played: overall.played,
+            won: overall.won,
+            win_rate: overall.win_rate(),
+            total_score: overall.score,
+            difficulties,
+        }
+    }
+
+    /// Accounts with at least one win at `difficulty`, fastest first.
+    pub fn leaderboard(&self, difficulty: ClientDifficulty, limit: usize) -> Vec<LeaderboardEntry> {
+        let mut tallies: HashMap<&AccountId, Tally> = HashMap::new();
+        for record in self.records.iter().filter(|r| r.difficulty == difficulty) {
+            for participant in &record.participants {
+                if let Some(account_id) = &participant.account_id {
+                    tallies
+                        .entry(account_id)
+                        .or_default()
+                        .add(record, participant);
+                }
+            }
+        }
+        let mut ranked = tallies
+            .into_iter()
+            .filter_map(|(account_id, tally)| Some((tally.best_time_ms?, account_id, tally)))
+            .collect::<Vec<_>>();
+        ranked.sort_by(|(a_time, a_id, _), (b_time, b_id, _)| {
+            a_time.cmp(b_time).then_with(|| a_id.cmp(b_id))
+        });
+        ranked
+            .into_iter()
+            .take(limit)
+            .zip(1..)
+            .map(
+                |((best_time_ms, account_id, tally), rank)| LeaderboardEntry {
+                    rank,
+                    account_id: account_id.clone(),
+                    display_name: tally.display_name.clone(),
+                    best_time_ms,
+                    played: tally.played,
+                    won: tally.won,
+                    win_rate: tally.win_rate(),
+                },
+            )
+            .collect()
+    }
+}
+
+/// A match record's line, taken so it can be appended away from the caller's task.
+pub struct PendingAppend {
+    path: PathBuf,
+    line: Vec<u8>,
+}
+
+impl PendingAppend {
+    pub fn write(&self) -> Result<(), StatsError> {
+        if let Some(parent) = self
+            .path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(&self.line)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::protocol::wire::ClientDifficulty;
+
+    use super::{MatchMode, MatchOutcome, MatchParticipant, MatchRecord, StatsStore};
+
+    fn participant(account_id: &str, won: bool, score: u32) -> MatchParticipant {
+        MatchParticipant {
+            player_id: format!("P-{account_id}"),
+            account_id: Some(account_id.to_string()),
+            display_name: Some(account_id.to_uppercase()),
+            won,
+            score,
+        }
+    }
+
+    fn record(
+        difficulty: ClientDifficulty,
+        duration_ms: u64,
+        participants: Vec<MatchParticipant>,
+    ) -> MatchRecord {
+        let outcome = if participants.iter().any(|p| p.won) {
+            MatchOutcome::Won
+        } else {
+            MatchOutcome::NoWinner
+        };
+        MatchRecord {
+            room_code: "quiet-otter".to_string(),
+            mode: MatchMode::TurnBased,
+            difficulty,
+            width: 8,
+            height: 8,
+            mines: 6,
+            seed: 1,
+            duration_ms,
+            outcome,
+            finished_at: 0,
+            participants,
+        }
+    }
+
+    #[test]
+    fn player_stats_track_win_rates_and_best_times_per_difficulty() {
+        let mut store = StatsStore::in_memory();
+        let easy = ClientDifficulty::Easy;
+        store
+            .record(record(easy, 9_000, vec![participant("ada", true, 5)]))
+            .unwrap();
+        store
+            .record(record(easy, 7_000, vec![participant("ada", true, 4)]))
+            .unwrap();
+        store
+            .record(record(easy, 3_000, vec![participant("ada", false, 1)]))
+            .unwrap();
+
+        let stats = store.player_stats(&"ada".to_string());
+        assert_eq!((stats.played, stats.won, stats.total_score), (3, 2, 10));
+        assert_eq!(stats.difficulties.len(), 1);
+        assert_eq!(stats.difficulties[0].best_time_ms, Some(7_000));
+        assert!((stats.win_rate - 2.0 / 3.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn leaderboards_rank_winners_by_best_time() {
+        let mut store = StatsStore::in_memory();
+        let hard = ClientDifficulty::Hard;
+        store
+            .record(record(
+                hard,
+                40_000,
+                vec![participant("ada", true, 9), participant("bob", false, 2)],
+            ))
+            .unwrap();
+        store
+            .record(record(hard, 30_000, vec![participant("cy", true, 9)]))
+            .unwrap();
+        store
+            .record(record(
+                ClientDifficulty::Easy,
+                1_000,
+                vec![participant("bob", true, 3)],
+            ))
+            .unwrap();
+
+        let board = store.leaderboard(hard, 10);
+        let ranked = board
+            .iter()
+            .map(|entry| (entry.rank, entry.account_id.as_str(), entry.best_time_ms))
+            .collect::<Vec<_>>();
+        assert_eq!(ranked, vec![(1, "cy", 30_000), (2, "ada", 40_000)]);
+        assert_eq!(store.leaderboard(hard, 1).len(), 1);
+    }
+
+    #[test]
+    fn corrupt_lines_are_skipped_but_kept_in_the_file() {
+        let path = std::env::temp_dir().join(format!(
+            "multisweeper-stats-{:016x}.jsonl",
+            rand::random::<u64>()
+        ));
+        let good = serde_json::to_string(&record(
+            ClientDifficulty::Easy,
+            1_000,
+            vec![participant("ada", true, 1)],
+        ))
+        .expect("record should serialize");
+        let torn = &good[..good.len() / 2];
+        std::fs::write(&path, format!("{good}\nnot json\n{torn}"))
+            .expect("temp file should be writable");
+
+        let mut store = StatsStore::open(&path).expect("bad lines should not stop loading");
+        assert_eq!(store.len(), 1);
+        store
+            .record(record(ClientDifficulty::Easy, 2_000, Vec::new()))
+            .expect("append should succeed");
+
+        let reopened = StatsStore::open(&path).expect("file should still load");
+        assert_eq!(reopened.len(), 2);
+        let contents = std::fs::read_to_string(&path).expect("file should be readable");
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[1..3], ["not json", torn]);
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,88 @@
+use std::{path::PathBuf, sync::Arc};
+
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::{
+    TlsAcceptor,
+    rustls::{
+        self, ServerConfig,
+        pki_types::{
+            CertificateDer, PrivateKeyDer,
+            pem::{self, PemObject},
+        },
+    },
+};
+
+#[derive(Debug, Error)]
+pub enum TlsError {
+    #[error("could not read PEM file {path}: {source}")]
+    Pem { path: PathBuf, source: pem::Error },
+    #[error("no certificates found in {0}")]
+    NoCertificates(PathBuf),
+    #[error("invalid TLS configuration: {0}")]
+    Config(#[from] rustls::Error),
+}
+
+/// Any byte stream a WebSocket can run over, so plain and TLS connections share one session type.
+pub trait ClientStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> ClientStream for T {}
+
+pub type ClientConnection = Box<dyn ClientStream>;
+
+/// Builds an acceptor for `wss://` from a PEM certificate chain and private key.
+pub fn load_acceptor(
+    cert_path: impl Into<PathBuf>,
+    key_path: impl Into<PathBuf>,
+) -> Result<TlsAcceptor, TlsError> {
+    let cert_path = cert_path.into();
+    let key_path = key_path.into();
+    let certs = CertificateDer::pem_file_iter(&cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|source| TlsError::Pem {
+            path: cert_path.clone(),
+            source,
+        })?;
+    if certs.is_empty() {
+        return Err(TlsError::NoCertificates(cert_path));
+    }
+    let key = PrivateKeyDer::from_pem_file(&key_path).map_err(|source| TlsError::Pem {
+        path: key_path,
+        source,
+    })?;
+
+    let mut config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TlsError, load_acceptor};
+
+    #[test]
+    fn unreadable_or_empty_certificates_are_rejected() {
+        let dir = std::env::temp_dir();
+        let missing = dir.join(format!(
+            "multisweeper-missing-{:016x}.pem",
+            rand::random::<u64>()
+        ));
+        assert!(matches!(
+            load_acceptor(&missing, &missing),
+            Err(TlsError::Pem { .. })
+        ));
+
+        let empty = dir.join(format!(
+            "multisweeper-empty-{:016x}.pem",
+            rand::random::<u64>()
+        ));
+        std::fs::write(&empty, "").expect("temp file should be writable");
+        assert!(matches!(
+            load_acceptor(&empty, &empty),
+            Err(TlsError::NoCertificates(_))
+        ));
+        let _ = std::fs::remove_file(empty);
+    }
+}